@@ -0,0 +1,69 @@
+use std::path::PathBuf;
+
+/// A REPL meta-command, these start with a `:` and control the session
+/// rather than being evaluated as math expressions.
+#[derive(Debug, Clone)]
+pub(crate) enum Command {
+    Vars,
+    Del(Vec<String>),
+    Clear,
+    Reset,
+    Save(PathBuf),
+    Load(PathBuf),
+    Paste,
+    Help,
+    Quit,
+}
+
+/// Names of all the meta-commands (without the leading `:`).
+pub(crate) const NAMES: &[&str] = &[
+    "vars", "del", "clear", "reset", "save", "load", "paste", "help", "quit",
+];
+
+pub(crate) const HELP: &str = "\
+:vars         list all variables
+:del NAME...  remove variables
+:clear        remove all variables
+:reset        start a fresh session (rerunning the init file and --define etc.)
+:save FILE    save variables to FILE as a script
+:load FILE    evaluate the script FILE in this session
+:paste        enter a block of lines (ctrl-d to finish) and evaluate it as a script
+:help         show this message
+:quit         exit the repl";
+
+impl Command {
+    /// Parses a meta-command from an input line, returns `None` if the line
+    /// isn't a meta-command (doesn't start with `:`).
+    pub fn parse(line: &str) -> Option<Result<Self, String>> {
+        let mut words = line.trim().strip_prefix(':')?.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        let args = words.collect::<Vec<_>>();
+
+        let path = |args: &[&str]| match args {
+            [path] => Ok(PathBuf::from(path)),
+            _ => Err(format!("':{name}' takes exactly one file path")),
+        };
+        let none = |cmd: Self| {
+            if args.is_empty() {
+                Ok(cmd)
+            } else {
+                Err(format!("':{name}' doesn't take any arguments"))
+            }
+        };
+
+        Some(match name {
+            "vars" => none(Self::Vars),
+            "del" if args.is_empty() => Err("':del' takes at least one variable name".to_owned()),
+            "del" => Ok(Self::Del(args.iter().map(|x| x.to_string()).collect())),
+            "clear" => none(Self::Clear),
+            "reset" => none(Self::Reset),
+            "save" => path(&args).map(Self::Save),
+            "load" => path(&args).map(Self::Load),
+            "paste" => none(Self::Paste),
+            "help" => none(Self::Help),
+            "quit" | "q" => none(Self::Quit),
+            "" => Err("missing command name, try ':help'".to_owned()),
+            _ => Err(format!("unknown command ':{name}', try ':help'")),
+        })
+    }
+}
@@ -0,0 +1,210 @@
+use std::{fs, path::PathBuf};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use jnk::{unit::Unit, value, Integer};
+use rustyline::EditMode;
+use toml::{Table, Value};
+
+/// User configuration, read from `config.toml` in the config directory
+/// (`$XDG_CONFIG_HOME/jnk`, or `~/.config/jnk`). Command line arguments take
+/// precedence over anything set here.
+#[derive(Debug, Clone)]
+pub(crate) struct Config {
+    /// repl prompt, replaces the default `jnk >> `
+    pub prompt: Option<String>,
+    /// forces colour output on or off, by default it's detected
+    pub color: Option<bool>,
+    /// key bindings used by the repl line editor
+    pub edit_mode: EditMode,
+    /// radix values are printed in (2, 8, 10 or 16)
+    pub radix: u32,
+    /// repl history file
+    pub history: Option<PathBuf>,
+}
+
+/// The kinds of number jnk can compute with, only integers are supported
+/// for now.
+const MODES: &[&str] = &["integer"];
+
+const KEYS: &[&str] = &["prompt", "color", "edit_mode", "radix", "mode", "history"];
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            prompt: None,
+            color: None,
+            edit_mode: EditMode::Emacs,
+            radix: 10,
+            history: None,
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config file, if there is one.
+    pub fn load() -> Result<Self> {
+        match crate::paths::config_dir().map(|x| x.join("config.toml")) {
+            Some(path) if path.exists() => fs::read_to_string(&path)
+                .wrap_err("unable to read config file")
+                .and_then(|x| Self::from_str(&x))
+                .wrap_err_with(|| format!("invalid config file '{}'", path.display())),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    fn from_str(config: &str) -> Result<Self> {
+        let table: Table = config.parse()?;
+        if let Some(key) = table.keys().find(|x| !KEYS.contains(&x.as_str())) {
+            return Err(eyre!(
+                "unknown key '{key}', expected one of: {}",
+                KEYS.join(", ")
+            ));
+        }
+
+        let mut config = Self::default();
+        for (key, value) in table {
+            match (key.as_str(), value) {
+                ("prompt", Value::String(x)) => config.prompt = Some(x),
+                ("color", Value::Boolean(x)) => config.color = Some(x),
+                ("edit_mode", Value::String(x)) => {
+                    config.edit_mode = match x.as_str() {
+                        "emacs" => EditMode::Emacs,
+                        "vi" => EditMode::Vi,
+                        _ => return Err(eyre!("edit_mode must be \"emacs\" or \"vi\", not '{x}'")),
+                    }
+                }
+                ("radix", Value::Integer(x)) => {
+                    config.radix = match x {
+                        2 | 8 | 10 | 16 => x as u32,
+                        _ => return Err(eyre!("radix must be 2, 8, 10 or 16, not {x}")),
+                    }
+                }
+                ("mode", Value::String(x)) => {
+                    if !MODES.contains(&x.as_str()) {
+                        return Err(eyre!(
+                            "unsupported mode '{x}', expected one of: {}",
+                            MODES.join(", ")
+                        ));
+                    }
+                }
+                ("history", Value::String(x)) => {
+                    config.history = Some(match x.strip_prefix("~/") {
+                        Some(x) => crate::paths::home_dir()
+                            .ok_or_else(|| eyre!("unable to expand '~', $HOME not set"))?
+                            .join(x),
+                        None => PathBuf::from(x),
+                    })
+                }
+                (key, value) => {
+                    return Err(eyre!("wrong type for '{key}', found {}", value.type_str()))
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    /// Formats a value in the configured radix, with a `0b`, `0o` or `0x`
+    /// prefix if it isn't decimal.
+    pub fn format(&self, value: &Integer) -> String {
+        let prefix = match self.radix {
+            2 => "0b",
+            8 => "0o",
+            16 => "0x",
+            _ => return value.to_string(),
+        };
+        let digits = value.to_string_radix(self.radix as i32);
+        match digits.strip_prefix('-') {
+            Some(x) => format!("-{prefix}{x}"),
+            None => format!("{prefix}{digits}"),
+        }
+    }
+
+    /// Same as [`Self::format()`], followed by `unit` if it isn't empty.
+    pub fn format_unit(&self, value: &Integer, unit: &Unit) -> String {
+        match unit.is_empty() {
+            true => self.format(value),
+            false => format!("{} {unit}", self.format(value)),
+        }
+    }
+
+    /// Same as [`Self::format_unit()`] for numbers, lists are formatted as
+    /// `[1, 2, 3]`.
+    pub fn format_value(&self, value: &value::Value) -> String {
+        match value {
+            value::Value::Number(x) => self.format_unit(&x.value, &x.unit),
+            value::Value::List(values) => {
+                let values = values.iter().map(|x| self.format_value(x));
+                format!("[{}]", values.collect::<Vec<_>>().join(", "))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(config: &str) -> String {
+        format!("{:#}", Config::from_str(config).unwrap_err())
+    }
+
+    #[test]
+    fn parse() {
+        let config = Config::from_str(
+            r#"
+            prompt = "> "
+            color = false
+            edit_mode = "vi"
+            radix = 16
+            mode = "integer"
+            history = "/tmp/history"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.prompt.as_deref(), Some("> "));
+        assert_eq!(config.color, Some(false));
+        assert_eq!(config.edit_mode, EditMode::Vi);
+        assert_eq!(config.radix, 16);
+        assert_eq!(config.history, Some(PathBuf::from("/tmp/history")));
+
+        let config = Config::from_str("").unwrap();
+        assert_eq!(config.prompt, None);
+        assert_eq!(config.edit_mode, EditMode::Emacs);
+        assert_eq!(config.radix, 10);
+    }
+
+    #[test]
+    fn validation() {
+        assert_eq!(
+            error("modes = \"integer\""),
+            "unknown key 'modes', expected one of: prompt, color, edit_mode, radix, mode, history"
+        );
+        assert_eq!(
+            error("mode = \"float\""),
+            "unsupported mode 'float', expected one of: integer"
+        );
+        assert_eq!(error("radix = 3"), "radix must be 2, 8, 10 or 16, not 3");
+        assert_eq!(
+            error("edit_mode = \"nano\""),
+            "edit_mode must be \"emacs\" or \"vi\", not 'nano'"
+        );
+        assert_eq!(
+            error("color = \"yes\""),
+            "wrong type for 'color', found string"
+        );
+        assert!(Config::from_str("prompt = ").is_err());
+    }
+
+    #[test]
+    fn formatting() {
+        let mut config = Config::default();
+        assert_eq!(config.format(&Integer::from(-255)), "-255");
+        config.radix = 16;
+        assert_eq!(config.format(&Integer::from(-255)), "-0xff");
+        config.radix = 2;
+        assert_eq!(
+            config.format_unit(&Integer::from(5), &"m".parse().unwrap()),
+            "0b101 m"
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use std::borrow::Cow;
+
+use jnk::{
+    context::{MathContext, FUNCTIONS},
+    token::{tokenize, Token, TokenKind},
+};
+use owo_colors::{OwoColorize, Stream::Stdout, Style};
+use rustyline::{
+    completion::{Completer, FilenameCompleter, Pair},
+    highlight::Highlighter,
+    hint::Hinter,
+    validate::{ValidationContext, ValidationResult, Validator},
+    Context, Helper,
+};
+
+use crate::commands;
+
+/// Line editor helper for the REPL, provides tab completion of variables,
+/// functions and meta-commands, and syntax highlighting of expressions.
+pub(crate) struct JnkHelper {
+    vars: Vec<String>,
+    files: FilenameCompleter,
+}
+
+impl JnkHelper {
+    pub fn new() -> Self {
+        Self {
+            vars: Vec::new(),
+            files: FilenameCompleter::new(),
+        }
+    }
+
+    /// Updates the variable names offered for completion, should be called
+    /// whenever the context changes.
+    pub fn update(&mut self, ctx: &MathContext) {
+        self.vars = ctx.vars_value().map(|(name, _)| name.to_owned()).collect();
+    }
+
+    fn complete_vars<'a>(&'a self, word: &'a str) -> impl Iterator<Item = Pair> + 'a {
+        self.vars
+            .iter()
+            .filter(move |x| x.starts_with(word))
+            .map(|x| pair(x, x.to_owned()))
+    }
+}
+
+impl Completer for JnkHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..pos]
+            .char_indices()
+            .rev()
+            .find(|(_, x)| !(x.is_ascii_alphanumeric() || *x == '_'))
+            .map_or(0, |(i, x)| i + x.len_utf8());
+        let word = &line[start..pos];
+
+        if let Some(cmd) = line.trim_start().strip_prefix(':') {
+            return match cmd.split_once(char::is_whitespace) {
+                None => Ok((
+                    start,
+                    commands::NAMES
+                        .iter()
+                        .filter(|x| x.starts_with(word))
+                        .map(|x| pair(x, x.to_string()))
+                        .collect(),
+                )),
+                Some(("load" | "save", _)) => self.files.complete(line, pos, ctx),
+                Some(("del", _)) => Ok((start, self.complete_vars(word).collect())),
+                Some(_) => Ok((pos, Vec::new())),
+            };
+        }
+
+        let functions = FUNCTIONS
+            .iter()
+            .filter(|x| x.starts_with(word))
+            .map(|x| pair(x, format!("{x}(")));
+        Ok((start, self.complete_vars(word).chain(functions).collect()))
+    }
+}
+
+fn pair(display: &str, replacement: String) -> Pair {
+    Pair {
+        display: display.to_owned(),
+        replacement,
+    }
+}
+
+impl Hinter for JnkHelper {
+    type Hint = String;
+}
+
+impl Highlighter for JnkHelper {
+    fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
+        if line.trim_start().starts_with(':') {
+            return Cow::Borrowed(line);
+        }
+
+        let tokens = tokenize(line);
+        let parens = match_parens(line, &tokens);
+        // the paren under (or just before) the cursor and its partner
+        let cursor = tokens
+            .iter()
+            .position(|x| x.kind == TokenKind::Paren && x.span.start == pos)
+            .or_else(|| {
+                tokens
+                    .iter()
+                    .position(|x| x.kind == TokenKind::Paren && x.span.end == pos)
+            })
+            .and_then(|i| Some((i, parens[i]?)));
+
+        let mut bound = Vec::new();
+        let mut out = String::with_capacity(line.len() * 2);
+        let mut end = 0;
+        for (i, token) in tokens.iter().enumerate() {
+            let text = &line[token.span.clone()];
+            let next = tokens.get(i + 1).map(|x| &line[x.span.clone()]);
+            let style = match token.kind {
+                TokenKind::Literal => Style::new().cyan(),
+                TokenKind::Unit => Style::new().cyan().italic(),
+                TokenKind::Keyword => {
+                    if text == "let"
+                        && tokens.get(i + 1).map(|x| x.kind) == Some(TokenKind::Variable)
+                    {
+                        bound.push(next.unwrap_or_default());
+                    }
+                    Style::new().magenta()
+                }
+                TokenKind::Variable
+                    if (i == 0 && next == Some("="))
+                        || bound.contains(&text)
+                        || self.vars.iter().any(|x| x == text)
+                        || text == "_" =>
+                {
+                    Style::new().green()
+                }
+                TokenKind::Function if FUNCTIONS.contains(&text) => {
+                    // the variable bound by e.g. `sum(k, 1, 10, k ^ 2)`
+                    let binds = matches!(text, "map" | "filter" | "sum" | "prod")
+                        && tokens.get(i + 3).map(|x| &line[x.span.clone()]) == Some(",");
+                    let var = tokens.get(i + 2).filter(|x| x.kind == TokenKind::Variable);
+                    if let Some(var) = var.filter(|_| binds) {
+                        bound.push(&line[var.span.clone()]);
+                    }
+                    Style::new().blue()
+                }
+                TokenKind::Operator => Style::new().yellow(),
+                TokenKind::Paren if parens[i].is_none() => Style::new().red().bold(),
+                TokenKind::Paren if cursor.is_some_and(|(a, b)| a == i || b == i) => {
+                    Style::new().bright_blue().bold()
+                }
+                TokenKind::Paren | TokenKind::Punctuation => Style::new(),
+                // a trailing `;` suppresses the value
+                TokenKind::Unknown if text == ";" && line[token.span.end..].trim().is_empty() => {
+                    Style::new()
+                }
+                _ => Style::new().red().underline(),
+            };
+            out.push_str(&line[end..token.span.start]);
+            out += &text
+                .if_supports_color(Stdout, |x| x.style(style))
+                .to_string();
+            end = token.span.end;
+        }
+        out.push_str(&line[end..]);
+        Cow::Owned(out)
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        // parens are highlighted based on the cursor position, so the line
+        // always needs redrawing
+        true
+    }
+}
+
+/// Finds the index of the matching parenthesis (or bracket) for every token,
+/// `None` for tokens which aren't parentheses or are unmatched.
+fn match_parens(line: &str, tokens: &[Token]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; tokens.len()];
+    let mut open = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind != TokenKind::Paren {
+            continue;
+        }
+        match &line[token.span.clone()] {
+            "(" | "[" => open.push(i),
+            close => {
+                // a `)` can't close a `[` and vice versa
+                let open = open.pop().filter(|j| {
+                    matches!(
+                        (&line[tokens[*j].span.clone()], close),
+                        ("(", ")") | ("[", "]")
+                    )
+                });
+                if let Some(j) = open {
+                    matches[i] = Some(j);
+                    matches[j] = Some(i);
+                }
+            }
+        }
+    }
+    matches
+}
+
+impl Validator for JnkHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        let input = ctx.input();
+        if input.trim_start().starts_with([':', '#']) {
+            return Ok(ValidationResult::Valid(None));
+        }
+
+        // keep reading lines while there are unclosed parens (or brackets) or the line
+        // ends with a `\` continuation
+        let tokens = tokenize(input);
+        let unclosed = match_parens(input, &tokens)
+            .iter()
+            .zip(&tokens)
+            .any(|(x, token)| x.is_none() && matches!(&input[token.span.clone()], "(" | "["));
+        if unclosed || input.trim_end().ends_with('\\') {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
+impl Helper for JnkHelper {}
+
+#[cfg(test)]
+mod tests {
+    use rustyline::history::DefaultHistory;
+
+    use super::*;
+
+    fn complete(helper: &JnkHelper, line: &str) -> (usize, Vec<String>) {
+        let history = DefaultHistory::new();
+        let (start, pairs) = helper
+            .complete(line, line.len(), &Context::new(&history))
+            .unwrap();
+        (start, pairs.into_iter().map(|x| x.replacement).collect())
+    }
+
+    #[test]
+    fn completion() {
+        let mut helper = JnkHelper::new();
+        let mut ctx = MathContext::new();
+        ctx.eval("total = 1").unwrap();
+        JnkHelper::update(&mut helper, &ctx);
+
+        assert_eq!(complete(&helper, "2 * tota"), (4, vec!["total".to_owned()]));
+        assert_eq!(complete(&helper, "isp"), (0, vec!["isprime(".to_owned()]));
+        assert_eq!(complete(&helper, ":he"), (1, vec!["help".to_owned()]));
+        // words start after non-ascii characters, whatever their length
+        assert_eq!(complete(&helper, "é").0, 2);
+        assert_eq!(complete(&helper, "étota"), (2, vec!["total".to_owned()]));
+        assert_eq!(complete(&helper, "€tota"), (3, vec!["total".to_owned()]));
+    }
+}
@@ -0,0 +1,169 @@
+use std::{collections::HashSet, fs, io, path::PathBuf};
+
+use color_eyre::eyre::{Context, Result};
+use rustyline::{history::DefaultHistory, Editor, Helper};
+
+/// REPL history persisted across sessions, stored as plain text with one
+/// entry per line (oldest first) so it can be searched with other tools too.
+#[derive(Debug, Clone)]
+pub(crate) struct History {
+    path: Option<PathBuf>,
+    size: usize,
+    session: Vec<String>,
+}
+
+impl History {
+    /// Creates a history saved to `path` (if any) that keeps at most `size`
+    /// entries.
+    pub fn new(path: Option<PathBuf>, size: usize) -> Self {
+        Self {
+            path,
+            size,
+            session: Vec::new(),
+        }
+    }
+
+    /// Loads previously saved entries into the editor's history.
+    pub fn load<H: Helper>(&self, rl: &mut Editor<H, DefaultHistory>) -> Result<()> {
+        for entry in self.read()? {
+            rl.add_history_entry(entry)?;
+        }
+        Ok(())
+    }
+
+    /// Adds an entry to the editor's history and records it to be saved,
+    /// blank entries and ones starting with a space are ignored.
+    pub fn add<H: Helper>(
+        &mut self,
+        rl: &mut Editor<H, DefaultHistory>,
+        entry: &str,
+    ) -> Result<()> {
+        if let Some(entry) = self.record(entry) {
+            rl.add_history_entry(entry)?;
+        }
+        Ok(())
+    }
+
+    /// Records an entry to be saved, joining the lines of multi-line input,
+    /// returns the recorded entry unless it was ignored.
+    fn record(&mut self, entry: &str) -> Option<&str> {
+        if entry.trim().is_empty() || entry.starts_with(' ') {
+            return None;
+        }
+        let entry = entry.lines().map(str::trim).collect::<Vec<_>>().join(" ");
+        self.session.push(entry);
+        self.session.last().map(String::as_str)
+    }
+
+    /// Saves this session's entries after the ones currently in the history
+    /// file (so concurrent sessions don't overwrite each other), keeping
+    /// only the latest copy of duplicate entries.
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let mut entries = self.read()?;
+        entries.extend(self.session.iter().cloned());
+
+        let mut seen = HashSet::new();
+        let mut entries = entries
+            .into_iter()
+            .rev()
+            .filter(|x| seen.insert(x.clone()))
+            .take(self.size)
+            .collect::<Vec<_>>();
+        entries.reverse();
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).wrap_err("unable to create history directory")?;
+        }
+        let tmp = path.with_extension("tmp");
+        fs::write(
+            &tmp,
+            entries
+                .iter()
+                .map(|x| x.to_owned() + "\n")
+                .collect::<String>(),
+        )
+        .and_then(|_| fs::rename(&tmp, path))
+        .wrap_err("unable to write history file")
+    }
+
+    fn read(&self) -> Result<Vec<String>> {
+        let Some(path) = &self.path else {
+            return Ok(Vec::new());
+        };
+        match fs::read_to_string(path) {
+            Ok(x) => Ok(x
+                .lines()
+                .filter(|x| !x.trim().is_empty())
+                .map(str::to_owned)
+                .collect()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e).wrap_err("unable to read history file"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a history file path unique to `name`, removing any left over
+    /// from a previous run.
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("jnk-history-{}", std::process::id()));
+        let path = dir.join(name);
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn session(path: &Option<PathBuf>, size: usize, entries: &[&str]) -> History {
+        let mut history = History::new(path.to_owned(), size);
+        for entry in entries {
+            history.record(entry);
+        }
+        history
+    }
+
+    #[test]
+    fn ignored_entries() {
+        let mut history = History::new(None, 10);
+        assert_eq!(history.record("   "), None);
+        assert_eq!(history.record(" x = 1"), None);
+        assert_eq!(history.record("f(1,\n  2)"), Some("f(1, 2)"));
+        assert_eq!(history.session, ["f(1, 2)"]);
+        // without a path nothing is saved
+        history.save().unwrap();
+    }
+
+    #[test]
+    fn keeps_latest_duplicate() {
+        let path = Some(temp_path("dedup"));
+        session(&path, 10, &["a", "b", "a"]).save().unwrap();
+        assert_eq!(History::new(path.clone(), 10).read().unwrap(), ["b", "a"]);
+
+        // later sessions are appended after the saved entries
+        session(&path, 10, &["b", "c"]).save().unwrap();
+        assert_eq!(History::new(path, 10).read().unwrap(), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn size_cap() {
+        let path = Some(temp_path("cap"));
+        session(&path, 3, &["1", "2", "3", "4", "5"])
+            .save()
+            .unwrap();
+        assert_eq!(History::new(path, 3).read().unwrap(), ["3", "4", "5"]);
+    }
+
+    #[test]
+    fn writes_through_temporary_file() {
+        let path = temp_path("nested/history");
+        session(&Some(path.clone()), 10, &["x"]).save().unwrap();
+        // the parent directory is created and the temporary file renamed
+        assert_eq!(fs::read_to_string(&path).unwrap(), "x\n");
+        assert!(!path.with_extension("tmp").exists());
+    }
+}
@@ -4,56 +4,240 @@
 
 use std::{io::Read, path::PathBuf};
 
-use clap::Parser;
-use color_eyre::eyre::Result;
+use clap::{Parser, Subcommand};
+use color_eyre::eyre::{eyre, Result};
+use config::Config;
+use jnk::context::MathContext;
 use once_cell::sync::Lazy;
-use owo_colors::{OwoColorize, Stream::Stdout};
+use output::{Format, Output};
+use owo_colors::{
+    OwoColorize,
+    Stream::{Stderr, Stdout},
+    Style,
+};
+use status::{Failure, Status};
 
+mod commands;
+mod config;
+mod helper;
+mod history;
+mod output;
 mod parser;
+mod paths;
+mod preload;
 mod repl;
 mod scripts;
+mod status;
+mod testing;
 
 const CRATE: &str = env!("CARGO_CRATE_NAME");
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const AUTHORS: &str = env!("CARGO_PKG_AUTHORS");
 
 static ARGS: Lazy<Args> = Lazy::new(Args::parse);
+static CONFIG: Lazy<Config> = Lazy::new(|| {
+    Config::load().unwrap_or_else(|e| {
+        eprintln!(
+            "{} {:#}",
+            "WARNING".if_supports_color(Stderr, |x| x.style(Style::new().yellow().bold())),
+            e
+        );
+        Config::default()
+    })
+});
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
+#[command(after_help = "\
+Exit status:
+  0  success (or the status passed to `exit` in a script)
+  1  an expression couldn't be evaluated, or an assertion or test failed
+  2  invalid command line arguments
+  3  a script or expression couldn't be parsed
+  4  a file couldn't be read or written")]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// disable prompt prompt characters
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     quiet: bool,
 
+    /// file to save repl history to [default: $XDG_DATA_HOME/jnk/history]
+    #[arg(long, value_name = "FILE")]
+    history: Option<PathBuf>,
+
+    /// don't load or save repl history
+    #[arg(long, conflicts_with = "history")]
+    no_history: bool,
+
+    /// don't run the init file ($XDG_CONFIG_HOME/jnk/init.jnk) before
+    /// starting the repl
+    #[arg(long)]
+    no_init: bool,
+
+    /// maximum number of repl history entries to keep
+    #[arg(long, value_name = "N", default_value_t = 1000)]
+    history_size: usize,
+
+    /// report every failing line of a script instead of stopping at the first
+    #[arg(short, long, global = true)]
+    keep_going: bool,
+
+    /// maximum number of iterations of a single loop in a script, or of all
+    /// the sums, products, ranges and factorizations in an expression
+    #[arg(long, value_name = "N", default_value_t = 1_000_000, global = true)]
+    max_iterations: usize,
+
+    /// output format for evaluation results (check and test always print
+    /// text)
+    #[arg(short, long, value_enum, default_value_t, global = true)]
+    format: Format,
+
+    /// set variable NAME to VALUE before running, can be repeated (VALUE may
+    /// use a 0x, 0o or 0b prefix)
+    #[arg(
+        short = 'D',
+        long = "define",
+        visible_alias = "set",
+        value_name = "NAME=VALUE",
+        global = true
+    )]
+    defines: Vec<String>,
+
+    /// load variables from FILE before running, either a JSON object (.json)
+    /// or NAME=VALUE lines, can be repeated
+    #[arg(long, value_name = "FILE", global = true)]
+    vars_from: Vec<PathBuf>,
+
+    /// load environment variables starting with PREFIX as variables, with the
+    /// prefix removed and the name lowercased (e.g. JNK_RATE becomes rate)
+    #[arg(long, value_name = "PREFIX", global = true)]
+    env_prefix: Option<String>,
+
+    /// evaluate EXPR and print its result, can be repeated to evaluate several
+    /// expressions in order (sharing variables)
+    #[arg(short, long = "expr", value_name = "EXPR", conflicts_with = "file")]
+    exprs: Vec<String>,
+
     /// path to script to evaluate (`-' for stdin)
     file: Option<PathBuf>,
+
+    /// arguments to the script, available in it as arg1, arg2, ... (with the
+    /// number of arguments as argc)
+    #[arg(requires = "file", trailing_var_arg = true, allow_hyphen_values = true)]
+    args: Vec<String>,
 }
 
-fn main() -> Result<()> {
-    color_eyre::install()?;
+#[derive(Subcommand)]
+enum Command {
+    /// check scripts for errors without evaluating them
+    Check {
+        /// paths to scripts to check
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// run scripts as tests, reporting whether each assertion passes
+    Test {
+        /// scripts, or directories to search for test scripts (named
+        /// *_test.jnk or test_*.jnk)
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+}
 
-    if let Some(path) = &ARGS.file {
-        if *path == PathBuf::from("-") {
+fn main() {
+    let code = match color_eyre::install().and_then(|_| run()) {
+        Ok(code) => code,
+        Err(e) => {
+            // the report's location and backtrace would only point inside jnk
+            eprintln!(
+                "{} {e:#}",
+                "ERROR".if_supports_color(Stderr, |x| x.style(Style::new().red().bold()))
+            );
+            Status::of(&e) as u8
+        }
+    };
+    std::process::exit(code.into());
+}
+
+/// Creates a blank context, limited to `--max-iterations`.
+pub(crate) fn new_context() -> MathContext {
+    let mut ctx = MathContext::new();
+    ctx.set_max_iterations(ARGS.max_iterations);
+    ctx
+}
+
+/// Creates the context to evaluate in: a blank one, with the init file run if
+/// `init` is set, then the variables given on the command line (so they take
+/// precedence over the init file).
+pub(crate) fn context(init: bool) -> Result<MathContext> {
+    let mut ctx = new_context();
+    if init {
+        repl::init(&mut ctx);
+    }
+    preload::load(&mut ctx).map_err(|e| match Status::of(&e) {
+        Status::Io => e,
+        _ => e.wrap_err(Failure {
+            status: Status::Usage,
+            msg: "invalid arguments".to_owned(),
+        }),
+    })?;
+    Ok(ctx)
+}
+
+/// Runs jnk, returning the status to exit with.
+fn run() -> Result<u8> {
+    if let Some(color) = CONFIG.color {
+        owo_colors::set_override(color);
+    }
+
+    let repl = ARGS.command.is_none() && ARGS.exprs.is_empty() && ARGS.file.is_none();
+    let mut ctx = context(repl && !ARGS.no_init)?;
+    if ARGS.command.is_some() && ARGS.format != Format::Text {
+        // diagnostics and test results are only printed as text
+        return Err(Failure::report(
+            Status::Usage,
+            "--format can't be used with check or test",
+        ));
+    }
+    let mut out = Output::new(ARGS.format);
+    let res = if let Some(Command::Check { files }) = &ARGS.command {
+        let mut errors = 0;
+        for path in files {
+            errors += scripts::MathScript::from_file(path)?.check(&ctx);
+        }
+        match errors {
+            0 => Ok(None),
+            _ => Err(eyre!("found {errors} error(s)")),
+        }
+    } else if let Some(Command::Test { paths }) = &ARGS.command {
+        testing::run(paths).map(|_| None)
+    } else if !ARGS.exprs.is_empty() {
+        scripts::MathScript::from_exprs(&ARGS.exprs).eval(&mut ctx, &mut out, ARGS.keep_going)
+    } else if let Some(path) = &ARGS.file {
+        let script = if path.as_os_str() == "-" {
             let mut buf = String::new();
             std::io::stdin().lock().read_to_string(&mut buf)?;
-            scripts::MathScript::from_str(buf)?.eval()?;
+            scripts::MathScript::from_str("<stdin>".to_owned(), buf)?
         } else {
-            scripts::MathScript::from_file(path)?.eval()?;
-        }
+            scripts::MathScript::from_file(path)?
+        };
+        script.eval(&mut ctx, &mut out, ARGS.keep_going)
     } else {
-        if !ARGS.quiet {
+        if !ARGS.quiet && out.is_text() {
             println!(
                 "{} REPL {}{} (c) 2023 {}\n{}",
-                CRATE.bold(),
-                "v".bold(),
-                VERSION.bold(),
+                CRATE.if_supports_color(Stdout, |x| x.bold()),
+                "v".if_supports_color(Stdout, |x| x.bold()),
+                VERSION.if_supports_color(Stdout, |x| x.bold()),
                 AUTHORS,
-                "(press ctrl-d to exit)".if_supports_color(Stdout, |x| x.dimmed()),
+                "(press ctrl-d to exit, :help for commands)"
+                    .if_supports_color(Stdout, |x| x.dimmed()),
             );
         }
-        repl::run(&mut jnk::context::MathContext::new())?;
-    }
-
-    Ok(())
+        repl::run(&mut ctx, &mut out).map(|_| None)
+    };
+    out.finish();
+    res.map(Option::unwrap_or_default)
 }
@@ -0,0 +1,147 @@
+use clap::ValueEnum;
+use jnk::{context::Eval, error::Error};
+use serde::Serialize;
+
+/// Output format of evaluation results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub(crate) enum Format {
+    /// human readable text
+    #[default]
+    Text,
+    /// a JSON array of records
+    Json,
+    /// one JSON record per line
+    Jsonl,
+    /// CSV with a header row
+    Csv,
+}
+
+const CSV_HEADER: &str = "line,input,var,value,unit,error,message,start,end";
+
+/// Writes a record to stdout for every evaluated line in one of the machine
+/// readable formats, each has the input, the variable assigned (if any), the
+/// value as a decimal string and its unit (if it has one), and the error (if
+/// any) as its variant name, message and span within the input.
+#[derive(Debug)]
+pub(crate) struct Output {
+    format: Format,
+    records: usize,
+}
+
+impl Output {
+    pub fn new(format: Format) -> Self {
+        Self { format, records: 0 }
+    }
+
+    /// Returns true if results should be printed as human readable text
+    /// rather than through [`Self::record()`].
+    #[inline]
+    pub fn is_text(&self) -> bool {
+        self.format == Format::Text
+    }
+
+    pub fn record(&mut self, line: usize, input: &str, res: &Result<Eval, Error>) {
+        let (var, value, unit, error) = match res {
+            Ok(x) => match &x.value {
+                Some(value) => (
+                    x.var.as_deref(),
+                    Some(value.to_string()),
+                    Some(x.unit.to_string()).filter(|_| !x.unit.is_empty()),
+                    None,
+                ),
+                None => (x.var.as_deref(), Some(x.result().to_string()), None, None),
+            },
+            Err(e) => (None, None, None, Some(e)),
+        };
+        match self.format {
+            Format::Text => (),
+            Format::Json | Format::Jsonl => {
+                let record = JsonRecord {
+                    line,
+                    input,
+                    var,
+                    value: value.as_deref(),
+                    unit: unit.as_deref(),
+                    error: error.map(|e| JsonError {
+                        kind: e.name(),
+                        message: message(e),
+                        span: e.span().map(|x| [x.start, x.end]),
+                    }),
+                };
+                let json = serde_json::to_string(&record).unwrap();
+                if self.format == Format::Jsonl {
+                    println!("{json}");
+                } else if self.records == 0 {
+                    print!("[\n  {json}");
+                } else {
+                    print!(",\n  {json}");
+                }
+            }
+            Format::Csv => {
+                if self.records == 0 {
+                    println!("{CSV_HEADER}");
+                }
+                let span = error.and_then(|x| x.span());
+                println!(
+                    "{line},{},{},{},{},{},{},{},{}",
+                    csv_str(input),
+                    csv_str(var.unwrap_or_default()),
+                    csv_str(&value.unwrap_or_default()),
+                    csv_str(&unit.unwrap_or_default()),
+                    error.map_or("", |x| x.name()),
+                    csv_str(&error.map(message).unwrap_or_default()),
+                    span.as_ref().map_or(String::new(), |x| x.start.to_string()),
+                    span.as_ref().map_or(String::new(), |x| x.end.to_string()),
+                );
+            }
+        }
+        self.records += 1;
+    }
+
+    /// Finishes the output, must be called after the last record.
+    pub fn finish(&mut self) {
+        match self.format {
+            Format::Json if self.records == 0 => println!("[]"),
+            Format::Json => println!("\n]"),
+            Format::Csv if self.records == 0 => println!("{CSV_HEADER}"),
+            _ => (),
+        }
+    }
+}
+
+/// Returns a single line message for an error (parse errors are normally
+/// displayed over several lines).
+pub(crate) fn message(e: &Error) -> String {
+    match e {
+        Error::ParseError { parse_failure, .. } => {
+            format!("parse error: {}", parse_failure.variant.message())
+        }
+        e => e.to_string(),
+    }
+}
+
+/// A record in the JSON formats, see [`Output`].
+#[derive(Serialize)]
+struct JsonRecord<'a> {
+    line: usize,
+    input: &'a str,
+    var: Option<&'a str>,
+    value: Option<&'a str>,
+    unit: Option<&'a str>,
+    error: Option<JsonError<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonError<'a> {
+    kind: &'a str,
+    message: String,
+    span: Option<[usize; 2]>,
+}
+
+fn csv_str(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
@@ -0,0 +1,25 @@
+use std::{env, path::PathBuf};
+
+/// Returns the directory jnk stores its data in, following the XDG base
+/// directory spec (`$XDG_DATA_HOME/jnk`, or `~/.local/share/jnk`).
+pub(crate) fn data_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// Returns the directory jnk reads its configuration from, following the XDG
+/// base directory spec (`$XDG_CONFIG_HOME/jnk`, or `~/.config/jnk`).
+pub(crate) fn config_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+pub(crate) fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME").map(PathBuf::from)
+}
+
+fn xdg_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|x| x.is_absolute())
+        .or_else(|| home_dir().map(|x| x.join(fallback)))
+        .map(|x| x.join(crate::CRATE))
+}
@@ -0,0 +1,237 @@
+use std::{env, fs, path::Path};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use jnk::{context::MathContext, Integer};
+
+/// Seeds `ctx` with the variables given on the command line, in increasing
+/// order of precedence: environment variables starting with `--env-prefix`,
+/// `--vars-from` files (in the order given), `--define`s, then the script's
+/// arguments (`argc` and `arg1`, `arg2`, ...) if there is a script.
+pub(crate) fn load(ctx: &mut MathContext) -> Result<()> {
+    if let Some(prefix) = &crate::ARGS.env_prefix {
+        ctx.extend(from_env(prefix)?)?;
+    }
+    for path in &crate::ARGS.vars_from {
+        ctx.extend(
+            from_file(path).wrap_err_with(|| format!("unable to load '{}'", path.display()))?,
+        )?;
+    }
+    for define in &crate::ARGS.defines {
+        let (name, value) = define
+            .split_once('=')
+            .ok_or_else(|| eyre!("--define '{define}': expected NAME=VALUE"))?;
+        ctx.extend([var(name.trim(), value.trim()).wrap_err("invalid --define")?])?;
+    }
+    if crate::ARGS.file.is_some() {
+        let args = &crate::ARGS.args;
+        ctx.var_set("argc".to_owned(), args.len().into())?;
+        for (i, arg) in args.iter().enumerate() {
+            ctx.extend([var(&format!("arg{}", i + 1), arg).wrap_err("invalid script argument")?])?;
+        }
+    }
+    Ok(())
+}
+
+/// Collects every environment variable starting with `prefix`, the prefix is
+/// stripped and the rest of the name lowercased (so `JNK_RATE` becomes `rate`).
+fn from_env(prefix: &str) -> Result<Vec<(String, Integer)>> {
+    let mut vars = env::vars_os()
+        .filter_map(|(name, value)| {
+            let name = name.to_str()?.strip_prefix(prefix)?.to_lowercase();
+            Some((name, value))
+        })
+        .map(|(name, value)| {
+            let value = value.to_str().ok_or_else(|| {
+                eyre!(
+                    "environment variable '{prefix}{}' is not valid unicode",
+                    name.to_uppercase()
+                )
+            })?;
+            var(&name, value).wrap_err("invalid environment variable")
+        })
+        .collect::<Result<Vec<_>>>()?;
+    vars.sort();
+    Ok(vars)
+}
+
+/// Reads variables from a file, either a flat JSON object (if the extension is
+/// `.json`) mapping names to integers or strings of integers, or otherwise
+/// `.env` style `NAME=VALUE` lines (blank lines and `#` comments are skipped).
+fn from_file(path: &Path) -> Result<Vec<(String, Integer)>> {
+    let text = fs::read_to_string(path).wrap_err("unable to read file")?;
+    if path.extension().is_some_and(|x| x == "json") {
+        let map: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(&text).wrap_err("expected a JSON object")?;
+        map.into_iter()
+            .map(|(name, value)| match value {
+                serde_json::Value::String(x) => var(&name, &x),
+                serde_json::Value::Number(x) if x.is_f64() => Err(eyre!(
+                    "'{name}': {x} is not an integer (large integers should be quoted)"
+                )),
+                x @ serde_json::Value::Number(_) => var(&name, &x.to_string()),
+                x => Err(eyre!("'{name}': expected a number, found {x}")),
+            })
+            .collect()
+    } else {
+        text.lines()
+            .enumerate()
+            .map(|(i, x)| (i + 1, x.trim()))
+            .filter(|(_, x)| !x.is_empty() && !x.starts_with('#'))
+            .map(|(i, x)| {
+                let x = x.strip_prefix("export ").unwrap_or(x);
+                let (name, value) = x
+                    .split_once('=')
+                    .ok_or_else(|| eyre!("line {i}: expected NAME=VALUE"))?;
+                let value = value.trim();
+                let value = [('"', '"'), ('\'', '\'')]
+                    .iter()
+                    .find_map(|(l, r)| value.strip_prefix(*l)?.strip_suffix(*r))
+                    .unwrap_or(value);
+                var(name.trim(), value).wrap_err_with(|| format!("line {i}"))
+            })
+            .collect()
+    }
+}
+
+/// Validates a single name/value pair.
+fn var(name: &str, value: &str) -> Result<(String, Integer)> {
+    if !MathContext::var_valid(name) || name == "_" {
+        return Err(eyre!("'{name}' is not a valid variable name"));
+    }
+    Ok((
+        name.to_owned(),
+        integer(value).ok_or_else(|| eyre!("'{name}': '{value}' is not an integer"))?,
+    ))
+}
+
+/// Parses an integer with an optional sign and `0x`, `0o` or `0b` radix
+/// prefix, `_` may be used as a digit separator.
+fn integer(value: &str) -> Option<Integer> {
+    let (neg, value) = match value.strip_prefix('-') {
+        Some(x) => (true, x),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let (radix, digits) = [
+        ("0x", 16),
+        ("0X", 16),
+        ("0o", 8),
+        ("0O", 8),
+        ("0b", 2),
+        ("0B", 2),
+    ]
+    .iter()
+    .find_map(|(prefix, radix)| Some((*radix, value.strip_prefix(prefix)?)))
+    .unwrap_or((10, value));
+    let digits = digits.replace('_', "");
+    if digits.is_empty() || !digits.chars().all(|x| x.is_digit(radix)) {
+        return None;
+    }
+    let value = Integer::from_str_radix(&digits, radix as i32).ok()?;
+    Some(if neg { -value } else { value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `text` to a temporary file named `name`, returning its path.
+    fn temp_file(name: &str, text: &str) -> std::path::PathBuf {
+        let dir = env::temp_dir().join(format!("jnk-preload-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn integers() {
+        assert_eq!(integer("42"), Some(42.into()));
+        assert_eq!(integer("-42"), Some((-42).into()));
+        assert_eq!(integer("+42"), Some(42.into()));
+        assert_eq!(integer("0xff"), Some(255.into()));
+        assert_eq!(integer("-0XFF"), Some((-255).into()));
+        assert_eq!(integer("0o17"), Some(15.into()));
+        assert_eq!(integer("0b1010"), Some(10.into()));
+        assert_eq!(integer("1_000_000"), Some(1_000_000.into()));
+        assert_eq!(
+            integer("123456789012345678901234567890")
+                .unwrap()
+                .to_string(),
+            "123456789012345678901234567890"
+        );
+
+        for value in [
+            "", "-", "0x", "_", "1.5", "12a", "0b102", "--1", "+-1", " 1",
+        ] {
+            assert_eq!(integer(value), None, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn names() {
+        assert!(var("rate", "5").is_ok());
+        assert!(var("1x", "5").is_err());
+        assert!(var("_", "5").is_err());
+        assert!(var("x", "five").is_err());
+    }
+
+    #[test]
+    fn environment() {
+        env::set_var("JNK_PRELOAD_TEST_RATE", "0x10");
+        env::set_var("JNK_PRELOAD_TEST_COUNT", "-3");
+        let vars = from_env("JNK_PRELOAD_TEST_").unwrap();
+        assert_eq!(
+            vars,
+            [
+                ("count".to_owned(), (-3).into()),
+                ("rate".to_owned(), 16.into())
+            ]
+        );
+
+        env::set_var("JNK_PRELOAD_BAD_X", "1.5");
+        assert!(from_env("JNK_PRELOAD_BAD_").is_err());
+    }
+
+    #[test]
+    fn dotenv_files() {
+        let path = temp_file(
+            "vars.env",
+            "# comment\n\nexport a=1\nb = \"0x10\"\nc='-2'\n",
+        );
+        let vars = from_file(&path).unwrap();
+        assert_eq!(
+            vars,
+            [
+                ("a".to_owned(), 1.into()),
+                ("b".to_owned(), 16.into()),
+                ("c".to_owned(), (-2).into()),
+            ]
+        );
+
+        let path = temp_file("bad.env", "a=1\nb\n");
+        let err = from_file(&path).unwrap_err();
+        assert_eq!(format!("{err:#}"), "line 2: expected NAME=VALUE");
+    }
+
+    #[test]
+    fn json_files() {
+        let path = temp_file(
+            "vars.json",
+            r#"{"a": 1, "b": "123456789012345678901234567890", "c": "-0b11"}"#,
+        );
+        let vars = from_file(&path).unwrap();
+        assert_eq!(vars.len(), 3);
+        assert_eq!(vars[0], ("a".to_owned(), 1.into()));
+        assert_eq!(vars[1].1.to_string(), "123456789012345678901234567890");
+        assert_eq!(vars[2], ("c".to_owned(), (-3).into()));
+
+        for json in [r#"{"a": 1.5}"#, r#"{"a": true}"#, "[1, 2]", r#"{"1a": 1}"#] {
+            let path = temp_file("bad.json", json);
+            assert!(from_file(&path).is_err(), "{json}");
+        }
+        let path = temp_file("empty.json", "");
+        assert!(from_file(&path).is_err());
+        fs::remove_file(&path).unwrap();
+        assert!(from_file(&path).is_err());
+    }
+}
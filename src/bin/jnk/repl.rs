@@ -1,15 +1,70 @@
+use std::fmt::Display;
+
+use crate::commands::{self, Command};
+use crate::helper::JnkHelper;
+use crate::history::History;
+use crate::output::Output;
 use color_eyre::eyre::{eyre, Result};
-use owo_colors::{OwoColorize, Stream::Stdout};
-use rustyline::error::ReadlineError;
+use jnk::context::MathContext;
+use owo_colors::{OwoColorize, Stream::Stdout, Style};
+use rustyline::{error::ReadlineError, history::DefaultHistory, Editor};
 
-pub(crate) fn run(ctx: &mut jnk::context::MathContext) -> Result<()> {
-    let mut rl = rustyline::DefaultEditor::new()?;
-    let prompt = if !crate::ARGS.quiet {
-        format!("jnk {} ", ">>".bold())
-    } else {
-        String::new()
+pub(crate) fn run(ctx: &mut MathContext, out: &mut Output) -> Result<()> {
+    let config = rustyline::Config::builder()
+        .max_history_size(crate::ARGS.history_size)?
+        .history_ignore_dups(true)?
+        .history_ignore_space(true)
+        .edit_mode(crate::CONFIG.edit_mode)
+        .build();
+    let mut rl = Editor::with_config(config)?;
+    rl.set_helper(Some(JnkHelper::new()));
+
+    let path = match &crate::ARGS.history {
+        _ if crate::ARGS.no_history => None,
+        Some(x) => Some(x.to_owned()),
+        None if crate::CONFIG.history.is_some() => crate::CONFIG.history.to_owned(),
+        None => crate::paths::data_dir().map(|x| x.join("history")),
+    };
+    let mut history = History::new(path, crate::ARGS.history_size);
+    if let Err(e) = history.load(&mut rl) {
+        print_error("unable to load history:", format!("{e:#}"));
+    }
+
+    let res = repl(ctx, out, &mut rl, &mut history);
+    if let Err(e) = history.save() {
+        print_error("unable to save history:", format!("{e:#}"));
+    }
+    res
+}
+
+/// Silently evaluates the init file, if it exists, reporting any error.
+pub(crate) fn init(ctx: &mut MathContext) {
+    let Some(path) = crate::paths::config_dir().map(|x| x.join("init.jnk")) else {
+        return;
+    };
+    if path.exists() {
+        if let Err(e) = crate::scripts::MathScript::from_file(&path).and_then(|x| x.load(ctx)) {
+            print_error("unable to run init file:", format!("{e:#}"));
+        }
+    }
+}
+
+fn repl(
+    ctx: &mut MathContext,
+    out: &mut Output,
+    rl: &mut Editor<JnkHelper, DefaultHistory>,
+    history: &mut History,
+) -> Result<()> {
+    let prompt = match &crate::CONFIG.prompt {
+        _ if crate::ARGS.quiet => String::new(),
+        Some(x) => x.to_owned(),
+        None => format!("jnk {} ", ">>".if_supports_color(Stdout, |x| x.bold())),
     };
+    let mut line = 0;
     loop {
+        if let Some(helper) = rl.helper_mut() {
+            helper.update(ctx);
+        }
         let input = match rl.readline(&prompt) {
             Ok(x) => x,
             Err(e) => match e {
@@ -18,42 +73,150 @@ pub(crate) fn run(ctx: &mut jnk::context::MathContext) -> Result<()> {
                 _ => return Err(eyre!(e).wrap_err("unexpected error reading user input")),
             },
         };
-        match ctx.eval(&input) {
+        if let Err(e) = history.add(rl, &input) {
+            print_error("unable to add history entry:", format!("{e:#}"));
+        }
+        if let Some(cmd) = Command::parse(&input) {
+            match cmd {
+                Ok(Command::Quit) => break Ok(()),
+                Ok(Command::Paste) => paste(ctx, out, rl)?,
+                Ok(cmd) => run_command(ctx, out, cmd),
+                Err(e) => print_error("invalid command:", e),
+            }
+            continue;
+        }
+        // a trailing `;` suppresses the value, as in scripts
+        let (input, silent) = match input.trim_end().strip_suffix(';') {
+            Some(x) => (x.to_owned(), true),
+            None => (input, false),
+        };
+        let res = ctx.eval(&input);
+        if !out.is_text() {
+            line += 1;
+            out.record(line, &input, &res);
+            continue;
+        }
+        match res {
             Ok(x) => {
-                if x.var.is_none() {
+                if x.var.is_none() && !silent {
                     if crate::ARGS.quiet {
-                        println!("{}", x.value)
+                        println!("{}", crate::CONFIG.format_value(&x.result()))
                     } else {
-                        println!("-> {}", x.value.bold())
+                        println!(
+                            "-> {}",
+                            crate::CONFIG
+                                .format_value(&x.result())
+                                .if_supports_color(Stdout, |x| x.bold())
+                        )
                     }
                 }
             }
-            Err(e) => match e {
-                jnk::error::Error::VarNotFound(var) => println!(
-                    "{} {} '{}'",
-                    "ERROR".if_supports_color(Stdout, |x| x.red()).bold(),
-                    "variable not found:".bold(),
-                    var,
-                ),
-                jnk::error::Error::NotValidVar(var) => println!(
-                    "{} {} '{}'",
-                    "ERROR".if_supports_color(Stdout, |x| x.red()).bold(),
-                    "invalid variable name:".bold(),
-                    var,
-                ),
-                jnk::error::Error::ParseError { parse_failure, .. } => println!(
-                    "{} {}\n{}",
-                    "ERROR".if_supports_color(Stdout, |x| x.red()).bold(),
-                    "failed to parse expression:".bold(),
-                    parse_failure,
-                ),
-                _ => println!(
-                    "{} {}\n{}",
-                    "ERROR".if_supports_color(Stdout, |x| x.red()).bold(),
-                    "unexpected error:".bold(),
-                    e,
-                ),
-            },
+            Err(e) => print_eval_error(e),
+        }
+    }
+}
+
+fn run_command(ctx: &mut MathContext, out: &mut Output, cmd: Command) {
+    match cmd {
+        Command::Vars => {
+            for (name, value) in ctx.vars_value() {
+                println!(
+                    "{} = {}",
+                    name.if_supports_color(Stdout, |x| x.bold()),
+                    crate::CONFIG.format_value(value)
+                );
+            }
+        }
+        Command::Del(names) => {
+            for name in names {
+                if ctx.var_remove(&name).is_none() {
+                    print_error("variable not found:", format!("'{name}'"));
+                }
+            }
+        }
+        Command::Clear => ctx.var_clear(),
+        Command::Reset => match crate::context(!crate::ARGS.no_init) {
+            Ok(x) => *ctx = x,
+            Err(e) => print_error("unable to reset session:", format!("{e:#}")),
+        },
+        Command::Save(path) => {
+            if let Err(e) = crate::scripts::MathScript::save(ctx, &path) {
+                print_error("unable to save session:", format!("{e:#}"));
+            }
+        }
+        Command::Load(path) => {
+            if let Err(e) = crate::scripts::MathScript::from_file(&path)
+                .and_then(|x| x.eval(ctx, out, false).map(|_| ()))
+            {
+                print_error("unable to load script:", format!("{e:#}"));
+            }
+        }
+        Command::Help => println!("{}", commands::HELP),
+        Command::Paste => unreachable!("Command::Paste handled by repl loop"),
+        Command::Quit => unreachable!("Command::Quit handled by repl loop"),
+    }
+}
+
+/// Reads lines until ctrl-d and evaluates them in order as a script, so
+/// comments and `\` line continuations work the same as in script files.
+fn paste(
+    ctx: &mut MathContext,
+    out: &mut Output,
+    rl: &mut Editor<JnkHelper, DefaultHistory>,
+) -> Result<()> {
+    let prompt = if !crate::ARGS.quiet {
+        format!("{} ", "..".if_supports_color(Stdout, |x| x.dimmed()))
+    } else {
+        String::new()
+    };
+    let mut script = String::new();
+    loop {
+        match rl.readline(&prompt) {
+            Ok(x) => {
+                script.push_str(&x);
+                script.push('\n');
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(ReadlineError::Interrupted) => return Ok(()),
+            Err(e) => return Err(eyre!(e).wrap_err("unexpected error reading user input")),
+        }
+    }
+    if let Err(e) = crate::scripts::MathScript::from_str("<paste>".to_owned(), script)
+        .and_then(|x| x.eval(ctx, out, false).map(|_| ()))
+    {
+        print_error("unable to evaluate pasted script:", format!("{e:#}"));
+    }
+    Ok(())
+}
+
+fn print_error(title: &str, msg: impl Display) {
+    println!(
+        "{} {} {}",
+        "ERROR".if_supports_color(Stdout, |x| x.style(Style::new().red().bold())),
+        title.if_supports_color(Stdout, |x| x.bold()),
+        msg,
+    )
+}
+
+fn print_eval_error(e: jnk::error::Error) {
+    match e {
+        jnk::error::Error::VarNotFound(var) => {
+            print_error("variable not found:", format!("'{var}'"))
+        }
+        jnk::error::Error::NotValidVar(var) => {
+            print_error("invalid variable name:", format!("'{var}'"))
         }
+        jnk::error::Error::ParseError { parse_failure, .. } => println!(
+            "{} {}\n{}",
+            "ERROR".if_supports_color(Stdout, |x| x.style(Style::new().red().bold())),
+            "failed to parse expression:".if_supports_color(Stdout, |x| x.bold()),
+            parse_failure,
+        ),
+        _ => println!(
+            "{} {}\n{}",
+            "ERROR".if_supports_color(Stdout, |x| x.style(Style::new().red().bold())),
+            "unexpected error:".if_supports_color(Stdout, |x| x.bold()),
+            e,
+        ),
     }
 }
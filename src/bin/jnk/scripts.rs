@@ -1,68 +1,1103 @@
-use std::{fs, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
 
+use crate::output::{self, Output};
 use crate::parser::{FileParser, Rule};
-use color_eyre::eyre::{eyre, Context, Result};
-use pest::{iterators::Pair, Parser};
+use crate::status::{Failure, Status};
+use color_eyre::eyre::{Context, Result};
+use jnk::{
+    context::{Eval, MathContext},
+    error::Error,
+    unit::Quantity,
+    value::Value,
+    Integer,
+};
+use owo_colors::{
+    OwoColorize,
+    Stream::{Stderr, Stdout},
+    Style,
+};
+use pest::{error::InputLocation, iterators::Pair, Parser};
 
+/// A script of expressions, one per line, which can be grouped into blocks
+/// with `for var in a..b { }` (or `a..=b` to include `b`), `while cond { }`
+/// and `if cond { } else { }`, where conditions are true if non-zero. The
+/// variable of a `for` loop is an ordinary variable, so it keeps its value
+/// from the last iteration after the loop.
+///
+/// The value of each line which isn't an assignment is printed, unless it
+/// ends with `;`. `print "text"` prints a line of text, where `{expr}`
+/// interpolates the value of an expression, optionally formatted as
+/// `{expr:hex}`, `{expr:oct}`, `{expr:bin}` or `{expr:dec}` (use `{{` and
+/// `}}` for literal braces). Nothing is printed in machine readable formats.
+///
+/// `assert expr` fails if `expr` is zero, and `assert_eq a, b` fails if `a`
+/// and `b` aren't equal, see [`MathScript::test()`]. `exit expr` (or
+/// `return expr`) stops the script, with `expr` (mod 256) as its exit status
+/// (`0` if it's omitted).
 #[derive(Debug, Clone)]
 pub(crate) struct MathScript {
-    lines: Vec<String>,
+    sources: Vec<Source>,
+    body: Vec<Stmt>,
+}
+
+/// A file (or other input) lines of a script come from, the first source is
+/// the script itself and the rest are files it includes.
+#[derive(Debug, Clone)]
+struct Source {
+    name: String,
+    /// the source and position of the `include` directive that included this
+    /// source, if any
+    included_from: Option<(usize, usize, usize)>,
+}
+
+#[derive(Debug, Clone)]
+enum Stmt {
+    Line {
+        line: Line,
+        /// set if the line ends with `;`, so its value isn't printed
+        quiet: bool,
+    },
+    Print(Vec<Segment>),
+    Assert(Line),
+    AssertEq(Line, Line),
+    Exit(Option<Line>),
+    For {
+        var: String,
+        start: Line,
+        end: Line,
+        inclusive: bool,
+        body: Vec<Stmt>,
+    },
+    While {
+        cond: Line,
+        body: Vec<Stmt>,
+    },
+    If {
+        cond: Line,
+        then: Vec<Stmt>,
+        otherwise: Vec<Stmt>,
+    },
+}
+
+/// Part of the text of a `print` statement.
+#[derive(Debug, Clone)]
+enum Segment {
+    Text(String),
+    /// an interpolated expression, and the radix to format it in (or `None`
+    /// for the configured default)
+    Value(Line, Option<i32>),
+}
+
+/// A single expression of a script, along with where it starts in its source
+/// (a `\` continued expression can span multiple source lines, up to
+/// `end_line`).
+#[derive(Debug, Clone)]
+struct Line {
+    text: String,
+    source: usize,
+    line: usize,
+    end_line: usize,
+    col: usize,
+}
+
+impl Line {
+    fn new(text: String, source: usize, line: usize, col: usize) -> Self {
+        Self {
+            end_line: line + text.matches('\n').count(),
+            text,
+            source,
+            line,
+            col,
+        }
+    }
+
+    /// Returns the source line and column of the byte `offset` into the text.
+    fn position(&self, offset: usize) -> (usize, usize) {
+        let before = &self.text[..offset];
+        match before.rfind('\n') {
+            Some(i) => (
+                self.line + before.matches('\n').count(),
+                before[i + 1..].chars().count() + 1,
+            ),
+            None => (self.line, self.col + before.chars().count()),
+        }
+    }
+
+    /// Returns the offset of the first use of the variable `var` in the text.
+    fn find_var(&self, var: &str) -> Option<usize> {
+        let is_ident = |c: char| c == '_' || c.is_ascii_alphanumeric();
+        self.text.match_indices(var).map(|(i, _)| i).find(|&i| {
+            !self.text[..i].ends_with(is_ident) && !self.text[i + var.len()..].starts_with(is_ident)
+        })
+    }
+}
+
+/// A position in a script, displayed as `name:line:col`.
+#[derive(Debug, Clone)]
+struct Location<'a> {
+    name: &'a str,
+    line: usize,
+    col: usize,
+}
+
+impl fmt::Display for Location<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.name, self.line, self.col)
+    }
+}
+
+/// The state of a single evaluation of a script.
+struct Run<'a> {
+    ctx: &'a mut MathContext,
+    /// where results are written, `None` to evaluate silently
+    out: Option<&'a mut Output>,
+    keep_going: bool,
+    /// set when running as a test, so every assertion and error is reported
+    testing: bool,
+    evaluated: usize,
+    passed: usize,
+    failed: usize,
+    /// the status of the first failure
+    status: Option<Status>,
 }
 
 impl MathScript {
+    /// Reads and parses a script file, along with any files it includes
+    /// (`include "path.jnk"` lines, resolved relative to the including file).
     pub fn from_file(file: &Path) -> Result<Self> {
-        Self::from_str(fs::read_to_string(file).wrap_err("unable to read script file")?)
-            .wrap_err("unable to parse script file")
+        let script = fs::read_to_string(file).wrap_err("unable to read script file")?;
+        let mut this = Self::new(file.display().to_string());
+        let mut stack = vec![file.canonicalize()?];
+        this.body = this
+            .parse(
+                0,
+                &script,
+                file.parent().unwrap_or(Path::new("")),
+                &mut stack,
+            )
+            .wrap_err("unable to parse script file")?;
+        Ok(this)
+    }
+
+    /// Parses a script from a string, `name` is used to refer to the script
+    /// in error messages. Included files are resolved relative to the current
+    /// directory.
+    pub fn from_str(name: String, script: String) -> Result<Self> {
+        let mut this = Self::new(name);
+        this.body = this.parse(0, &script, Path::new(""), &mut Vec::new())?;
+        Ok(this)
     }
 
-    pub fn from_str(mut script: String) -> Result<Self> {
+    /// Creates a script with each of `exprs` as a line, errors refer to the
+    /// expressions by their (1-based) index as if it were a line number.
+    pub fn from_exprs(exprs: &[String]) -> Self {
+        let mut this = Self::new("<expr>".to_owned());
+        this.body = exprs
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                Self::statement(Line::new(
+                    x.trim().to_owned(),
+                    0,
+                    i + 1,
+                    1 + x.len() - x.trim_start().len(),
+                ))
+            })
+            .collect();
+        this
+    }
+
+    fn new(name: String) -> Self {
+        Self {
+            sources: vec![Source {
+                name,
+                included_from: None,
+            }],
+            body: Vec::new(),
+        }
+    }
+
+    /// Parses `script` as the contents of `source`, returning its statements
+    /// (with those of any files it includes in their place). `stack` holds
+    /// the canonical paths of the files currently being included, to detect
+    /// cycles.
+    fn parse(
+        &mut self,
+        source: usize,
+        script: &str,
+        dir: &Path,
+        stack: &mut Vec<PathBuf>,
+    ) -> Result<Vec<Stmt>> {
+        let mut script = script.to_owned();
         if !script.ends_with('\n') {
             script.push('\n');
         }
+        match FileParser::parse(Rule::Main, &script)
+            .wrap_err("parser failure:")?
+            .next()
+        {
+            Some(x) => self.parse_block(source, x.into_inner(), dir, stack),
+            None => Ok(Vec::new()),
+        }
+    }
 
-        Ok(Self::from_pairs(
-            match FileParser::parse(Rule::Main, &script)
-                .wrap_err("parser failure:")?
-                .next()
-            {
-                Some(x) => x,
-                None => return Ok(Self { lines: Vec::new() }),
-            },
+    fn parse_block<'i>(
+        &mut self,
+        source: usize,
+        pairs: impl IntoIterator<Item = Pair<'i, Rule>>,
+        dir: &Path,
+        stack: &mut Vec<PathBuf>,
+    ) -> Result<Vec<Stmt>> {
+        let mut body = Vec::new();
+        for pair in pairs {
+            match pair.as_rule() {
+                Rule::Line => body.push(Self::statement(Self::line(source, pair))),
+                Rule::Print => body.push(self.print(source, pair)?),
+                Rule::Assert => {
+                    let expr = pair.into_inner().next().unwrap();
+                    body.push(Stmt::Assert(Self::line(source, expr)));
+                }
+                Rule::AssertEq => body.push(self.assert_eq(source, pair)?),
+                Rule::Exit => {
+                    let value = pair.into_inner().next();
+                    body.push(Stmt::Exit(value.map(|x| Self::line(source, x))));
+                }
+                Rule::Include => body.extend(self.include(source, pair, dir, stack)?),
+                Rule::For => {
+                    let mut pairs = pair.into_inner();
+                    let var = pairs.next().unwrap().as_str().to_owned();
+                    let start = Self::line(source, pairs.next().unwrap());
+                    let inclusive = pairs.next().unwrap().as_str() == "..=";
+                    let end = Self::line(source, pairs.next().unwrap());
+                    let inner = pairs.next().unwrap().into_inner();
+                    body.push(Stmt::For {
+                        var,
+                        start,
+                        end,
+                        inclusive,
+                        body: self.parse_block(source, inner, dir, stack)?,
+                    });
+                }
+                Rule::While => {
+                    let mut pairs = pair.into_inner();
+                    let cond = Self::line(source, pairs.next().unwrap());
+                    let inner = pairs.next().unwrap().into_inner();
+                    body.push(Stmt::While {
+                        cond,
+                        body: self.parse_block(source, inner, dir, stack)?,
+                    });
+                }
+                Rule::If => {
+                    let mut pairs = pair.into_inner();
+                    let cond = Self::line(source, pairs.next().unwrap());
+                    let then = pairs.next().unwrap().into_inner();
+                    let then = self.parse_block(source, then, dir, stack)?;
+                    let otherwise = match pairs.next() {
+                        Some(x) if x.as_rule() == Rule::If => {
+                            self.parse_block(source, [x], dir, stack)?
+                        }
+                        Some(x) => self.parse_block(source, x.into_inner(), dir, stack)?,
+                        None => Vec::new(),
+                    };
+                    body.push(Stmt::If {
+                        cond,
+                        then,
+                        otherwise,
+                    });
+                }
+                Rule::EOI => (),
+                Rule::Main
+                | Rule::Statement
+                | Rule::Block
+                | Rule::Path
+                | Rule::Keyword
+                | Rule::Var
+                | Rule::Expr
+                | Rule::RangeOp
+                | Rule::Text
+                | Rule::Rest => unreachable!("Nested Rule ({:?})", pair.as_rule()),
+                Rule::COMMENT => unreachable!("Non-Silent Silent Rule (COMMENT)"),
+                Rule::WHITESPACE => unreachable!("Non-Silent Silent Rule (WHITESPACE)"),
+            }
+        }
+        Ok(body)
+    }
+
+    fn line(source: usize, pair: Pair<Rule>) -> Line {
+        let (line, col) = pair.line_col();
+        Line::new(pair.as_str().trim().to_owned(), source, line, col)
+    }
+
+    /// Creates the statement for an expression line, stripping the `;` which
+    /// suppresses its output.
+    fn statement(mut line: Line) -> Stmt {
+        let quiet = line.text.ends_with(';');
+        if quiet {
+            line.text.pop();
+            line.text.truncate(line.text.trim_end().len());
+        }
+        Stmt::Line { line, quiet }
+    }
+
+    /// Splits the expression of an `assert_eq` statement at the comma
+    /// separating its two sides (commas inside function calls and lists don't
+    /// count).
+    fn assert_eq(&self, source: usize, pair: Pair<Rule>) -> Result<Stmt> {
+        let expr = Self::line(source, pair.into_inner().next().unwrap());
+        let mut depth = 0;
+        let mut commas = expr.text.char_indices().filter(|(_, c)| {
+            match c {
+                '(' | '[' => depth += 1,
+                ')' | ']' => depth -= 1,
+                _ => (),
+            }
+            *c == ',' && depth == 0
+        });
+        let (Some((i, _)), None) = (commas.next(), commas.next()) else {
+            let msg = "assert_eq takes two expressions separated by ','";
+            let msg = format!("{}: {msg}{}", self.location(&expr), self.chain(source));
+            return Err(Failure::report(Status::Parse, msg));
+        };
+        let (lhs, rhs) = (&expr.text[..i], &expr.text[i + 1..]);
+        let (line, col) = expr.position(expr.text.len() - rhs.trim_start().len());
+        Ok(Stmt::AssertEq(
+            Line::new(lhs.trim().to_owned(), source, expr.line, expr.col),
+            Line::new(rhs.trim().to_owned(), source, line, col),
         ))
     }
 
-    #[inline]
-    fn from_pairs(pair: Pair<Rule>) -> Self {
-        Self {
-            lines: Self::from_pairs_internal(pair, Vec::new()),
+    /// Parses the text of a `print` statement into literal text and
+    /// interpolated expressions.
+    fn print(&self, source: usize, pair: Pair<Rule>) -> Result<Stmt> {
+        let text = pair.into_inner().next().unwrap();
+        let (line, col) = text.line_col();
+        let fail = |i: usize, msg: &str| {
+            let location = Location {
+                name: &self.sources[source].name,
+                line,
+                col: col + i,
+            };
+            let msg = format!("{location}: {msg}{}", self.chain(source));
+            Failure::report(Status::Parse, msg)
+        };
+
+        let mut segments = Vec::new();
+        let mut buf = String::new();
+        let mut chars = text.as_str().chars().enumerate().peekable();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => buf.push('\n'),
+                    Some((_, 't')) => buf.push('\t'),
+                    Some((_, x)) => buf.push(x),
+                    None => unreachable!("escape at end of print text"),
+                },
+                '{' | '}' if chars.next_if(|(_, x)| *x == c).is_some() => buf.push(c),
+                '}' => return Err(fail(i, "unmatched '}' in print text (use '}}')")),
+                '{' => {
+                    let mut expr = String::new();
+                    loop {
+                        match chars.next() {
+                            Some((_, '}')) => break,
+                            Some((_, x)) => expr.push(x),
+                            None => return Err(fail(i, "unclosed '{' in print text")),
+                        }
+                    }
+                    let (expr, radix) = match expr.split_once(':') {
+                        Some((expr, spec)) => match spec.trim() {
+                            "hex" => (expr, Some(16)),
+                            "oct" => (expr, Some(8)),
+                            "bin" => (expr, Some(2)),
+                            "dec" => (expr, Some(10)),
+                            spec => {
+                                let msg = format!(
+                                    "unknown format '{spec}', expected hex, oct, bin or dec"
+                                );
+                                return Err(fail(i, &msg));
+                            }
+                        },
+                        None => (expr.as_str(), None),
+                    };
+                    if !buf.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut buf)));
+                    }
+                    let expr = Line::new(
+                        expr.trim().to_owned(),
+                        source,
+                        line,
+                        col + i + 1 + expr.len() - expr.trim_start().len(),
+                    );
+                    segments.push(Segment::Value(expr, radix));
+                }
+                x => buf.push(x),
+            }
+        }
+        if !buf.is_empty() {
+            segments.push(Segment::Text(buf));
+        }
+        Ok(Stmt::Print(segments))
+    }
+
+    /// Reads and parses the file included by an `include` directive.
+    fn include(
+        &mut self,
+        source: usize,
+        pair: Pair<Rule>,
+        dir: &Path,
+        stack: &mut Vec<PathBuf>,
+    ) -> Result<Vec<Stmt>> {
+        let (line, col) = pair.line_col();
+        let location = Location {
+            name: &self.sources[source].name,
+            line,
+            col,
+        };
+        let path = dir.join(pair.into_inner().as_str());
+        let res = path
+            .canonicalize()
+            .and_then(|x| Ok((fs::read_to_string(&x)?, x)));
+        let (script, canonical) = match res {
+            Ok(x) => x,
+            Err(e) => {
+                let msg = format!("unable to include '{}': {e}", path.display());
+                let msg = format!("{location}: {msg}{}", self.chain(source));
+                return Err(Failure::report(Status::Io, msg));
+            }
+        };
+        if let Some(i) = stack.iter().position(|x| *x == canonical) {
+            let cycle = stack[i..]
+                .iter()
+                .chain([&canonical])
+                .map(|x| x.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            let msg = format!("include cycle: {cycle}");
+            let msg = format!("{location}: {msg}{}", self.chain(source));
+            return Err(Failure::report(Status::Parse, msg));
+        }
+
+        self.sources.push(Source {
+            name: path.display().to_string(),
+            included_from: Some((source, line, col)),
+        });
+        stack.push(canonical);
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let body = self.parse(self.sources.len() - 1, &script, dir, stack)?;
+        stack.pop();
+        Ok(body)
+    }
+
+    /// Returns the chain of includes that led to `source`, one
+    /// `included from name:line:col` line per include (empty if `source`
+    /// wasn't included).
+    fn chain(&self, mut source: usize) -> String {
+        let mut chain = String::new();
+        while let Some((from, line, col)) = self.sources[source].included_from {
+            let name = &self.sources[from].name;
+            chain.push_str(&format!("\n  included from {name}:{line}:{col}"));
+            source = from;
+        }
+        chain
+    }
+
+    /// Evaluates the script in `ctx`, printing the value of lines which
+    /// aren't assignments. Evaluation stops at the first error unless
+    /// `keep_going` is set, in which case every error is reported and an
+    /// error is returned once the whole script has been evaluated. Returns
+    /// the script's exit status if it stopped with `exit`.
+    pub fn eval(
+        &self,
+        ctx: &mut MathContext,
+        out: &mut Output,
+        keep_going: bool,
+    ) -> Result<Option<u8>> {
+        let mut run = Run {
+            ctx,
+            out: Some(out),
+            keep_going,
+            testing: false,
+            evaluated: 0,
+            passed: 0,
+            failed: 0,
+            status: None,
+        };
+        let code = self.run(&mut run, &self.body)?;
+        match run.failed {
+            _ if code.is_some() => Ok(code),
+            0 => Ok(None),
+            failed => Err(Failure::report(
+                run.status.unwrap_or(Status::Eval),
+                format!(
+                    "{failed} of {} lines in {} failed",
+                    run.evaluated, self.sources[0].name
+                ),
+            )),
+        }
+    }
+
+    /// Evaluates the script in `ctx` without printing anything, stopping at
+    /// the first error (or `exit`).
+    pub fn load(&self, ctx: &mut MathContext) -> Result<()> {
+        let mut run = Run {
+            ctx,
+            out: None,
+            keep_going: false,
+            testing: false,
+            evaluated: 0,
+            passed: 0,
+            failed: 0,
+            status: None,
+        };
+        self.run(&mut run, &self.body).map(|_| ())
+    }
+
+    /// Evaluates the script in `ctx` as a test, printing whether each
+    /// assertion passed or failed (errors count as failures and don't stop
+    /// evaluation), and nothing else, exiting with a non-zero status counts
+    /// as a failure. Returns the number of assertions that passed and the
+    /// number of failures.
+    pub fn test(&self, ctx: &mut MathContext) -> Result<(usize, usize)> {
+        let mut run = Run {
+            ctx,
+            out: None,
+            keep_going: true,
+            testing: true,
+            evaluated: 0,
+            passed: 0,
+            failed: 0,
+            status: None,
+        };
+        let code = self.run(&mut run, &self.body)?;
+        if let Some(code) = code.filter(|x| *x != 0) {
+            println!(
+                "{} {} exited with status {code}",
+                "FAIL".if_supports_color(Stdout, |x| x.style(Style::new().red().bold())),
+                format!("{}:", self.sources[0].name).if_supports_color(Stdout, |x| x.bold()),
+            );
+            run.failed += 1;
         }
+        Ok((run.passed, run.failed))
     }
 
-    fn from_pairs_internal(pair: Pair<Rule>, mut vec: Vec<String>) -> Vec<String> {
-        match pair.as_rule() {
-            Rule::Main => {
-                for p in pair.into_inner() {
-                    vec = Self::from_pairs_internal(p, vec);
+    /// Evaluates `body`, returning the exit status if it stopped with `exit`.
+    fn run(&self, run: &mut Run, body: &[Stmt]) -> Result<Option<u8>> {
+        'stmts: for stmt in body {
+            match stmt {
+                Stmt::Line { line, quiet } => {
+                    let res = run.ctx.eval(&line.text);
+                    run.evaluated += 1;
+                    match &mut run.out {
+                        Some(out) if !out.is_text() => out.record(line.line, &line.text, &res),
+                        Some(_) if !quiet => {
+                            if let Ok(x @ Eval { var: None, .. }) = &res {
+                                println!("{}", crate::CONFIG.format_value(&x.result()))
+                            }
+                        }
+                        _ => (),
+                    }
+                    if let Err(e) = res {
+                        self.fail(run, line, &e)?;
+                    }
+                }
+                Stmt::Print(segments) => {
+                    let mut text = String::new();
+                    for segment in segments {
+                        match segment {
+                            Segment::Text(x) => text.push_str(x),
+                            Segment::Value(line, radix) => {
+                                // the error is reported instead of a partial line
+                                let Some(value) = self.result(run, line)? else {
+                                    continue 'stmts;
+                                };
+                                text.push_str(&match (radix, value.as_number()) {
+                                    (Some(x), Some(value)) => value.value.to_string_radix(*x),
+                                    _ => crate::CONFIG.format_value(&value),
+                                });
+                            }
+                        }
+                    }
+                    if run.out.as_ref().is_some_and(|x| x.is_text()) {
+                        println!("{text}");
+                    }
+                }
+                Stmt::Assert(line) => {
+                    let Some(value) = self.value(run, line)? else {
+                        continue;
+                    };
+                    let failure = (value == 0).then(String::new);
+                    self.assertion(run, line, &line.text, failure)?;
+                }
+                Stmt::AssertEq(lhs, rhs) => {
+                    let (Some(a), Some(b)) = (self.result(run, lhs)?, self.result(run, rhs)?)
+                    else {
+                        continue;
+                    };
+                    let equal = match a.equals(&b) {
+                        Ok(x) => x,
+                        Err(e) => {
+                            self.fail(run, lhs, &e)?;
+                            continue;
+                        }
+                    };
+                    let failure = (!equal).then(|| {
+                        let a = crate::CONFIG.format_value(&a);
+                        let b = crate::CONFIG.format_value(&b);
+                        format!(" ({a} != {b})")
+                    });
+                    let expr = format!("{} == {}", lhs.text, rhs.text);
+                    self.assertion(run, lhs, &expr, failure)?;
+                }
+                Stmt::For {
+                    var,
+                    start,
+                    end,
+                    inclusive,
+                    body,
+                } => {
+                    let (Some(mut i), Some(mut end)) =
+                        (self.value(run, start)?, self.value(run, end)?)
+                    else {
+                        continue;
+                    };
+                    if *inclusive {
+                        end += 1;
+                    }
+                    if Integer::from(&end - &i) > crate::ARGS.max_iterations {
+                        self.fail_limit(run, start)?;
+                        continue;
+                    }
+                    while i < end {
+                        if let Err(e) = run.ctx.var_set(var.to_owned(), i.clone()) {
+                            self.fail(run, start, &e)?;
+                            break;
+                        }
+                        if let Some(code) = self.run(run, body)? {
+                            return Ok(Some(code));
+                        }
+                        i += 1;
+                    }
+                }
+                Stmt::While { cond, body } => {
+                    let mut iterations = 0;
+                    while self.value(run, cond)?.is_some_and(|x| x != 0) {
+                        if iterations == crate::ARGS.max_iterations {
+                            self.fail_limit(run, cond)?;
+                            break;
+                        }
+                        iterations += 1;
+                        if let Some(code) = self.run(run, body)? {
+                            return Ok(Some(code));
+                        }
+                    }
+                }
+                Stmt::If {
+                    cond,
+                    then,
+                    otherwise,
+                } => {
+                    let code = match self.value(run, cond)? {
+                        Some(x) if x != 0 => self.run(run, then)?,
+                        Some(_) => self.run(run, otherwise)?,
+                        None => None,
+                    };
+                    if code.is_some() {
+                        return Ok(code);
+                    }
+                }
+                Stmt::Exit(value) => {
+                    let value = match value {
+                        Some(line) => match self.value(run, line)? {
+                            Some(x) => x,
+                            None => continue,
+                        },
+                        None => Integer::new(),
+                    };
+                    let code = value.mod_u(256) as u8;
+                    return Ok(Some(code));
                 }
             }
-            Rule::Line => vec.push(pair.as_str().trim().to_owned()),
-            Rule::EOI => (),
-            Rule::COMMENT => unreachable!("Non-Silent Silent Rule (COMMENT)"),
-            Rule::WHITESPACE => unreachable!("Non-Silent Silent Rule (WHITESPACE)"),
+        }
+        Ok(None)
+    }
+
+    /// Evaluates a condition or range bound (which must be a plain number),
+    /// without assigning anything or updating the last value, returns `None`
+    /// if it failed (and evaluation should keep going).
+    fn value(&self, run: &mut Run, line: &Line) -> Result<Option<Integer>> {
+        let Some(res) = self.result(run, line)? else {
+            return Ok(None);
+        };
+        match res.into_number().and_then(Quantity::plain) {
+            Ok(x) => Ok(Some(x)),
+            Err(e) => self.fail(run, line, &e).map(|_| None),
+        }
+    }
+
+    /// Same as [`Self::value()`], for any value (including lists).
+    fn result(&self, run: &mut Run, line: &Line) -> Result<Option<Value>> {
+        run.evaluated += 1;
+        // evaluating in a scope discards any assignment and the last value
+        match run.ctx.with_scope(|ctx| ctx.eval(&line.text)) {
+            Ok(x) => Ok(Some(x.result())),
+            Err(e) => self.fail(run, line, &e).map(|_| None),
+        }
+    }
+
+    /// Reports an error on `line`, returning it unless evaluation should keep
+    /// going.
+    fn fail(&self, run: &mut Run, line: &Line, e: &Error) -> Result<()> {
+        let (location, msg) = self.describe(line, e);
+        let status = match e {
+            Error::ParseError { .. } => Status::Parse,
+            _ => Status::Eval,
+        };
+        self.report(run, line, location, msg, status)
+    }
+
+    fn fail_limit(&self, run: &mut Run, line: &Line) -> Result<()> {
+        let msg = format!(
+            "loop exceeded {} iterations (see --max-iterations)",
+            crate::ARGS.max_iterations
+        );
+        self.report(run, line, self.location(line), msg, Status::Eval)
+    }
+
+    /// Reports the result of an assertion of `expr`, `failure` is `None` if
+    /// it passed or some detail about why it failed. Failures are reported
+    /// like errors.
+    fn assertion(
+        &self,
+        run: &mut Run,
+        line: &Line,
+        expr: &str,
+        failure: Option<String>,
+    ) -> Result<()> {
+        if let Some(detail) = failure {
+            let msg = format!("assertion failed: {expr}{detail}");
+            return self.report(run, line, self.location(line), msg, Status::Eval);
+        }
+        if run.testing {
+            println!(
+                "{} {} {expr}",
+                "PASS".if_supports_color(Stdout, |x| x.style(Style::new().green().bold())),
+                format!("{}:", self.location(line)).if_supports_color(Stdout, |x| x.bold()),
+            );
+        }
+        run.passed += 1;
+        Ok(())
+    }
+
+    fn report(
+        &self,
+        run: &mut Run,
+        line: &Line,
+        location: Location,
+        msg: String,
+        status: Status,
+    ) -> Result<()> {
+        let chain = self.chain(line.source);
+        if !run.keep_going {
+            return Err(Failure::report(status, format!("{location}: {msg}{chain}")));
+        }
+        run.status.get_or_insert(status);
+        if run.testing {
+            println!(
+                "{} {} {}{chain}",
+                "FAIL".if_supports_color(Stdout, |x| x.style(Style::new().red().bold())),
+                format!("{location}:").if_supports_color(Stdout, |x| x.bold()),
+                msg,
+            );
+        } else if run.out.as_ref().is_some_and(|x| x.is_text()) {
+            eprintln!(
+                "{} {} {}{chain}",
+                "ERROR".if_supports_color(Stderr, |x| x.style(Style::new().red().bold())),
+                format!("{location}:").if_supports_color(Stderr, |x| x.bold()),
+                msg,
+            );
+        }
+        run.failed += 1;
+        Ok(())
+    }
+
+    /// Checks the script without evaluating it, printing a diagnostic for
+    /// every line that can't be parsed, every variable used before it's
+    /// assigned (errors) and every assignment that's never read (warnings).
+    /// Diagnostics are printed one per line as `name:line:col: level: msg`,
+    /// returns the number of errors. The variables of `ctx` (e.g. from
+    /// `--define`) count as assigned before the script.
+    pub fn check(&self, ctx: &MathContext) -> usize {
+        let mut checker = Checker {
+            script: self,
+            known: ctx.vars_value().map(|(name, _)| name.to_owned()).collect(),
+            defs: Vec::new(),
+            diagnostics: Vec::new(),
+        };
+        checker.block(&self.body, &mut HashMap::new(), true);
+        for (line, var, read) in std::mem::take(&mut checker.defs) {
+            if !read {
+                let msg = format!("value assigned to '{var}' is never read");
+                checker.diagnose(line, self.location(line), "warning", msg);
+            }
+        }
+
+        let mut diagnostics = checker.diagnostics;
+        diagnostics.sort_by_key(|(source, location, ..)| (*source, location.line, location.col));
+        for (source, location, level, msg) in &diagnostics {
+            println!("{location}: {level}: {msg}{}", self.chain(*source));
+        }
+        diagnostics
+            .iter()
+            .filter(|(_, _, level, _)| *level == "error")
+            .count()
+    }
+
+    fn location(&self, line: &Line) -> Location<'_> {
+        Location {
+            name: &self.sources[line.source].name,
+            line: line.line,
+            col: line.col,
+        }
+    }
+
+    /// Returns the location of the byte `offset` into the text of `line`.
+    fn location_at(&self, line: &Line, offset: usize) -> Location<'_> {
+        let (l, col) = line.position(offset);
+        Location {
+            line: l,
+            col,
+            ..self.location(line)
+        }
+    }
+
+    /// Returns where in the script an error occurred, and a message for it
+    /// (without the include chain, see [`Self::chain()`]). An error without a
+    /// position in a continued expression is reported where it starts, along
+    /// with the lines it spans.
+    fn describe(&self, line: &Line, e: &Error) -> (Location<'_>, String) {
+        let offset = match e {
+            Error::ParseError { parse_failure, .. } => match parse_failure.location {
+                InputLocation::Pos(x) | InputLocation::Span((x, _)) => Some(x),
+            },
+            Error::VarNotFound(var) => line.find_var(var),
+            _ => None,
         };
-        vec
+        let msg = output::message(e);
+        match offset {
+            Some(x) => (self.location_at(line, x), msg),
+            None if line.end_line > line.line => {
+                let lines = format!(
+                    " (in the expression on lines {}-{})",
+                    line.line, line.end_line
+                );
+                (self.location(line), msg + &lines)
+            }
+            None => (self.location(line), msg),
+        }
+    }
+
+    /// Writes the variables of `ctx` to `file` as a script of assignments,
+    /// which can be evaluated to restore them.
+    pub fn save(ctx: &MathContext, file: &Path) -> Result<()> {
+        let script = ctx
+            .vars_value()
+            .map(|(name, value)| format!("{name} = {value}\n"))
+            .collect::<String>();
+        fs::write(file, script).wrap_err("unable to write script file")
+    }
+}
+
+/// The assignments which may have set each variable at a point in a script,
+/// as indices into [`Checker::defs`].
+type Reaching = HashMap<String, Vec<usize>>;
+
+/// Finds the diagnostics for [`MathScript::check()`].
+struct Checker<'a> {
+    script: &'a MathScript,
+    /// variables assigned before the script
+    known: HashSet<String>,
+    /// every assignment, the variable it assigns and if it's ever read
+    defs: Vec<(&'a Line, String, bool)>,
+    diagnostics: Vec<(usize, Location<'a>, &'static str, String)>,
+}
+
+impl<'a> Checker<'a> {
+    /// Checks `body`, updating `reaching` with its assignments. Loops are
+    /// checked twice so values assigned in one iteration count as read by
+    /// the next, `report` is false the second time to avoid duplicate
+    /// diagnostics.
+    fn block(&mut self, body: &'a [Stmt], reaching: &mut Reaching, report: bool) {
+        for stmt in body {
+            match stmt {
+                Stmt::Line { line, .. } => self.line(line, reaching, report, true),
+                Stmt::Print(segments) => {
+                    for segment in segments {
+                        if let Segment::Value(line, _) = segment {
+                            self.line(line, reaching, report, false);
+                        }
+                    }
+                }
+                Stmt::Assert(line) => self.line(line, reaching, report, false),
+                Stmt::AssertEq(lhs, rhs) => {
+                    self.line(lhs, reaching, report, false);
+                    self.line(rhs, reaching, report, false);
+                }
+                Stmt::Exit(Some(line)) => self.line(line, reaching, report, false),
+                Stmt::Exit(None) => (),
+                Stmt::For {
+                    var,
+                    start,
+                    end,
+                    body,
+                    ..
+                } => {
+                    self.line(start, reaching, report, false);
+                    self.line(end, reaching, report, false);
+                    // the loop variable doesn't have to be read
+                    self.defs.push((start, var.to_owned(), true));
+                    reaching.insert(var.to_owned(), vec![self.defs.len() - 1]);
+                    let before = reaching.clone();
+                    self.block(body, reaching, report);
+                    merge(reaching, &before);
+                    self.block(body, reaching, false);
+                    merge(reaching, &before);
+                }
+                Stmt::While { cond, body } => {
+                    self.line(cond, reaching, report, false);
+                    let before = reaching.clone();
+                    self.block(body, reaching, report);
+                    merge(reaching, &before);
+                    self.line(cond, reaching, false, false);
+                    self.block(body, reaching, false);
+                    merge(reaching, &before);
+                    self.line(cond, reaching, false, false);
+                }
+                Stmt::If {
+                    cond,
+                    then,
+                    otherwise,
+                } => {
+                    self.line(cond, reaching, report, false);
+                    let mut other = reaching.clone();
+                    self.block(then, reaching, report);
+                    self.block(otherwise, &mut other, report);
+                    merge(reaching, &other);
+                }
+            }
+        }
     }
 
-    pub fn eval(&self) -> Result<()> {
-        let mut ctx = jnk::context::MathContext::new();
-        self.lines.iter().try_for_each(|x| match ctx.eval(x) {
-            Ok(x) => {
-                if x.var.is_none() {
-                    println!("{}", x.value)
+    /// Checks a single expression, `assigns` is false for conditions and
+    /// range bounds, which are evaluated without assigning anything.
+    fn line(&mut self, line: &'a Line, reaching: &mut Reaching, report: bool, assigns: bool) {
+        let analysis = match MathContext::analyze(&line.text) {
+            Ok(x) => x,
+            Err(e) => {
+                if report {
+                    let (location, msg) = self.script.describe(line, &e);
+                    self.diagnose(line, location, "error", msg);
                 }
-                Ok(())
+                return;
+            }
+        };
+        let mut undefined = Vec::new();
+        for var in analysis.reads {
+            match reaching.get(&var) {
+                Some(defs) => defs.iter().for_each(|&x| self.defs[x].2 = true),
+                None if var == "_" || self.known.contains(&var) || script_arg(&var) => (),
+                None if undefined.contains(&var) => (),
+                None => undefined.push(var),
+            }
+        }
+        if report {
+            for var in undefined {
+                let location = match line.find_var(&var) {
+                    Some(x) => self.script.location_at(line, x),
+                    None => self.script.location(line),
+                };
+                let msg = format!("'{var}' used before assignment");
+                self.diagnose(line, location, "error", msg);
             }
-            Err(e) => Err(eyre!(e)),
+        }
+        if let Some(var) = analysis.var.filter(|x| assigns && x != "_") {
+            // a line checked again (in a loop) is still the same assignment
+            let def = match self
+                .defs
+                .iter()
+                .position(|(x, _, _)| std::ptr::eq(*x, line))
+            {
+                Some(x) => x,
+                None => {
+                    self.defs.push((line, var.clone(), false));
+                    self.defs.len() - 1
+                }
+            };
+            reaching.insert(var, vec![def]);
+        }
+    }
+
+    fn diagnose(&mut self, line: &Line, location: Location<'a>, level: &'static str, msg: String) {
+        self.diagnostics.push((line.source, location, level, msg));
+    }
+}
+
+/// Returns true if `var` is one of the variables script arguments are bound to
+/// (`argc` and `arg1`, `arg2`, ...), which are assumed to be set when
+/// checking a script since its arguments aren't known.
+fn script_arg(var: &str) -> bool {
+    var == "argc"
+        || var.strip_prefix("arg").is_some_and(|x| {
+            !x.is_empty() && !x.starts_with('0') && x.bytes().all(|x| x.is_ascii_digit())
         })
+}
+
+/// Adds the assignments reaching from `other` to `reaching`.
+fn merge(reaching: &mut Reaching, other: &Reaching) {
+    for (var, defs) in other {
+        let entry = reaching.entry(var.to_owned()).or_default();
+        for def in defs {
+            if !entry.contains(def) {
+                entry.push(*def);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Describes the error evaluating the only line of `script`.
+    fn describe(script: &str) -> String {
+        let script = MathScript::from_str("test".to_owned(), script.to_owned()).unwrap();
+        let Some(Stmt::Line { line, .. }) = script.body.first() else {
+            panic!("expected a line");
+        };
+        let e = MathContext::new().eval(&line.text).unwrap_err();
+        let (location, msg) = script.describe(line, &e);
+        format!("{location}: {msg}")
+    }
+
+    #[test]
+    fn continued_line_errors() {
+        assert_eq!(
+            describe("x = 1 + \\\n  y * 2\n"),
+            "test:2:3: 'y' not in variable table"
+        );
+        assert_eq!(
+            describe("  x = 1 +\\\n 2 +\\\n 3 *\n"),
+            "test:3:5: parse error: expected Value"
+        );
+        assert_eq!(
+            describe("x = 1 + \\\n  2 / \\\n  0\n"),
+            "test:1:1: division by zero (in the expression on lines 1-3)"
+        );
+        assert_eq!(
+            describe("x = yy + y"),
+            "test:1:5: 'yy' not in variable table"
+        );
     }
 }
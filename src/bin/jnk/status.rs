@@ -0,0 +1,94 @@
+use std::{error, fmt, io};
+
+use color_eyre::eyre::Report;
+
+use crate::parser::Rule;
+
+/// The status the process exits with when it fails (a script can also exit
+/// with any status using `exit`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Status {
+    /// an expression couldn't be evaluated, an assertion or test failed, or
+    /// `check` found errors
+    Eval = 1,
+    /// the command line arguments are invalid
+    Usage = 2,
+    /// a script or expression couldn't be parsed
+    Parse = 3,
+    /// a file couldn't be read or written
+    Io = 4,
+}
+
+impl Status {
+    /// Finds the status to exit with for an error, errors which weren't
+    /// created as a [`Failure`] are classified by their cause.
+    pub fn of(report: &Report) -> Self {
+        if let Some(x) = report.downcast_ref::<Failure>() {
+            return x.status;
+        }
+        for e in report.chain() {
+            if let Some(x) = e.downcast_ref::<Failure>() {
+                return x.status;
+            } else if e.is::<io::Error>() {
+                return Status::Io;
+            } else if e.is::<pest::error::Error<Rule>>() {
+                return Status::Parse;
+            }
+        }
+        Status::Eval
+    }
+}
+
+/// An error along with the status the process should exit with because of it.
+#[derive(Debug)]
+pub(crate) struct Failure {
+    pub status: Status,
+    pub msg: String,
+}
+
+impl Failure {
+    pub fn report(status: Status, msg: impl Into<String>) -> Report {
+        Report::new(Self {
+            status,
+            msg: msg.into(),
+        })
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl error::Error for Failure {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color_eyre::eyre::eyre;
+
+    #[test]
+    fn classify() {
+        assert_eq!(Status::of(&eyre!("failed")), Status::Eval);
+        assert_eq!(
+            Status::of(&Failure::report(Status::Usage, "x")),
+            Status::Usage
+        );
+
+        let e = Report::new(io::Error::from(io::ErrorKind::NotFound));
+        assert_eq!(Status::of(&e.wrap_err("unable to read")), Status::Io);
+        let e = Failure::report(Status::Parse, "x").wrap_err(Failure {
+            status: Status::Usage,
+            msg: "invalid arguments".to_owned(),
+        });
+        assert_eq!(Status::of(&e), Status::Usage);
+        let e = Failure::report(Status::Parse, "x").wrap_err("context");
+        assert_eq!(Status::of(&e), Status::Parse);
+
+        assert_eq!(
+            [Status::Eval, Status::Usage, Status::Parse, Status::Io].map(|x| x as u8),
+            [1, 2, 3, 4]
+        );
+    }
+}
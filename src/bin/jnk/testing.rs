@@ -0,0 +1,80 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use owo_colors::{OwoColorize, Stream::Stdout, Style};
+
+use crate::scripts::MathScript;
+
+/// Runs every script in `paths` (and every test script in directories in
+/// `paths`, recursively, see [`is_test()`]) as a test, each with a fresh
+/// context, and prints a summary. Returns an error if any assertion failed or any script couldn't
+/// be evaluated.
+pub(crate) fn run(paths: &[PathBuf]) -> Result<()> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            scripts(path, &mut files)
+                .wrap_err_with(|| format!("unable to read directory '{}'", path.display()))?;
+        } else {
+            files.push(path.to_owned());
+        }
+    }
+
+    let (mut passed, mut failed) = (0, 0);
+    for file in &files {
+        match MathScript::from_file(file).and_then(|x| x.test(&mut crate::new_context())) {
+            Ok((p, f)) => {
+                passed += p;
+                failed += f;
+            }
+            Err(e) => {
+                println!(
+                    "{} {} {e:#}",
+                    "FAIL".if_supports_color(Stdout, |x| x.style(Style::new().red().bold())),
+                    format!("{}:", file.display()).if_supports_color(Stdout, |x| x.bold()),
+                );
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "\n{} {passed} passed, {failed} failed ({} file(s))",
+        "test result:".if_supports_color(Stdout, |x| x.bold()),
+        files.len(),
+    );
+    match failed {
+        0 => Ok(()),
+        _ => Err(eyre!("{failed} test failure(s)")),
+    }
+}
+
+/// Collects the test scripts in `dir` and its subdirectories, sorted by path.
+fn scripts(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|x| x.map(|x| x.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            scripts(&path, files)?;
+        } else if is_test(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if `path` is named like a test script (`*_test.jnk` or
+/// `test_*.jnk`), other scripts are left alone so they can be `include`d by
+/// tests without being run themselves.
+fn is_test(path: &Path) -> bool {
+    path.extension().is_some_and(|x| x == "jnk")
+        && path
+            .file_stem()
+            .and_then(|x| x.to_str())
+            .is_some_and(|x| x.ends_with("_test") || x.starts_with("test_"))
+}
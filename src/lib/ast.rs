@@ -1,4 +1,5 @@
 use crate::parser::Rule;
+use crate::value::Value;
 use crate::Integer;
 use std::str::FromStr;
 
@@ -10,7 +11,17 @@ pub(crate) enum Node {
     Operator(Operator),
     Parenthetical(Box<Node>),
     Negation(Box<Node>),
-    Literal(Integer),
+    Literal(Value),
+    /// a literal with a unit, e.g. `3 km`, the unit is looked up when it's
+    /// evaluated
+    Quantity(Integer, String),
+    /// a conversion of the rest of the expression, e.g. `to ft`
+    Conversion(String),
+    Function(String, Vec<Node>),
+    List(Vec<Node>),
+    /// an indexed value, e.g. `xs[0]`
+    Index(Box<Node>, Box<Node>),
+    Let(String, Box<Node>, Box<Node>),
     Expression(Vec<Option<Node>>),
 }
 
@@ -21,6 +32,14 @@ pub(crate) enum Operator {
     Multiplication,
     Division,
     Exponent,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Range,
+    RangeInclusive,
 }
 
 pub(crate) fn create_ast(pair: pest::iterators::Pair<Rule>) -> Node {
@@ -33,14 +52,49 @@ pub(crate) fn create_ast(pair: pest::iterators::Pair<Rule>) -> Node {
             "*" => Operator::Multiplication,
             "/" => Operator::Division,
             "^" => Operator::Exponent,
+            "==" => Operator::Equal,
+            "!=" => Operator::NotEqual,
+            "<" => Operator::Less,
+            "<=" => Operator::LessEqual,
+            ">" => Operator::Greater,
+            ">=" => Operator::GreaterEqual,
+            ".." => Operator::Range,
+            "..=" => Operator::RangeInclusive,
             _ => unreachable!("Not an operator string"),
         }),
         Rule::Parenthetical => {
             Node::Parenthetical(Box::new(create_ast(pair.into_inner().next().unwrap())))
         }
         Rule::Negation => Node::Negation(Box::new(create_ast(pair.into_inner().next().unwrap()))),
-        Rule::Literal => Node::Literal(Integer::from_str(pair.as_str()).unwrap()),
-        Rule::Value => create_ast(pair.into_inner().next().unwrap()),
+        Rule::Literal => Node::Literal(Integer::from_str(pair.as_str()).unwrap().into()),
+        Rule::Quantity => {
+            let mut pairs = pair.into_inner();
+            let value = Integer::from_str(pairs.next().unwrap().as_str()).unwrap();
+            Node::Quantity(value, pairs.next().unwrap().as_str().to_owned())
+        }
+        Rule::Conversion => Node::Conversion(pair.into_inner().next().unwrap().as_str().to_owned()),
+        Rule::Function => {
+            let mut pairs = pair.into_inner();
+            let name = pairs.next().unwrap().as_str().to_owned();
+            Node::Function(name, pairs.map(create_ast).collect())
+        }
+        Rule::Let => {
+            let mut pairs = pair.into_inner();
+            let name = pairs.next().unwrap().as_str().to_owned();
+            let value = create_ast(pairs.next().unwrap());
+            let body = create_ast(pairs.next().unwrap());
+            Node::Let(name, Box::new(value), Box::new(body))
+        }
+        Rule::List => Node::List(pair.into_inner().map(create_ast).collect()),
+        Rule::Value => {
+            let mut pairs = pair.into_inner();
+            let value = create_ast(pairs.next().unwrap());
+            // every following pair is an index, e.g. `xs[0][1]`
+            pairs.fold(value, |value, index| {
+                let index = create_ast(index.into_inner().next().unwrap());
+                Node::Index(Box::new(value), Box::new(index))
+            })
+        }
         Rule::Expression => {
             Node::Expression(pair.into_inner().map(|x| Some(create_ast(x))).collect())
         }
@@ -56,7 +110,120 @@ pub(crate) fn create_ast(pair: pest::iterators::Pair<Rule>) -> Node {
                 Node::Main(None, Box::new(create_ast(val)))
             }
         }
+        Rule::Keyword => unreachable!("Keyword only used in lookaheads"),
+        Rule::Index => unreachable!("Index only used in Value"),
+        Rule::Unit | Rule::UnitName => unreachable!("Unit only used in Quantity and Conversion"),
+        Rule::Tokens | Rule::Paren | Rule::Punctuation | Rule::Unknown => {
+            unreachable!("Rule::Tokens only used for tokenizing")
+        }
         Rule::EOI => unreachable!("Non-Silent Silent Rule (EOI)"),
         Rule::WHITESPACE => unreachable!("Non-Silent Silent Rule (WHITESPACE)"),
     }
 }
+
+/// Replaces every free occurrence of the variable `name` in `node` with the
+/// literal `value`, used to bind local variables (e.g. `let`) without
+/// touching the context's variable table.
+pub(crate) fn substitute(node: &mut Node, name: &str, value: &Value) {
+    match node {
+        Node::Variable(x) if x == name => *node = Node::Literal(value.clone()),
+        Node::Main(_, inner) | Node::Parenthetical(inner) | Node::Negation(inner) => {
+            substitute(inner, name, value)
+        }
+        Node::Function(function, args) => match binding(function, args) {
+            // the bound variable shadows this one in the body, but not in the
+            // other arguments
+            Some(var) if var == name => {
+                let body = args.len() - 1;
+                args[1..body]
+                    .iter_mut()
+                    .for_each(|x| substitute(x, name, value))
+            }
+            _ => args.iter_mut().for_each(|x| substitute(x, name, value)),
+        },
+        Node::List(items) => items.iter_mut().for_each(|x| substitute(x, name, value)),
+        Node::Index(inner, index) => {
+            substitute(inner, name, value);
+            substitute(index, name, value);
+        }
+        Node::Let(var, bound, body) => {
+            substitute(bound, name, value);
+            // an inner binding of the same name shadows this one
+            if var != name {
+                substitute(body, name, value);
+            }
+        }
+        Node::Expression(line) => line
+            .iter_mut()
+            .flatten()
+            .for_each(|x| substitute(x, name, value)),
+        Node::Variable(_)
+        | Node::Lhs(_)
+        | Node::Operator(_)
+        | Node::Literal(_)
+        | Node::Quantity(..)
+        | Node::Conversion(_) => (),
+    }
+}
+
+/// Collects the names of the free variables in `node` (ones not bound by an
+/// enclosing `let`) into `vars`, in the order they appear.
+pub(crate) fn free_vars(node: &Node, bound: &mut Vec<String>, vars: &mut Vec<String>) {
+    match node {
+        Node::Variable(x) if !bound.contains(x) => vars.push(x.to_owned()),
+        Node::Main(_, inner) | Node::Parenthetical(inner) | Node::Negation(inner) => {
+            free_vars(inner, bound, vars)
+        }
+        Node::Function(function, args) => match binding(function, args) {
+            Some(var) => {
+                let body = args.len() - 1;
+                args[1..body].iter().for_each(|x| free_vars(x, bound, vars));
+                bound.push(var.to_owned());
+                free_vars(&args[body], bound, vars);
+                bound.pop();
+            }
+            None => args.iter().for_each(|x| free_vars(x, bound, vars)),
+        },
+        Node::List(items) => items.iter().for_each(|x| free_vars(x, bound, vars)),
+        Node::Index(inner, index) => {
+            free_vars(inner, bound, vars);
+            free_vars(index, bound, vars);
+        }
+        Node::Let(var, value, body) => {
+            free_vars(value, bound, vars);
+            bound.push(var.to_owned());
+            free_vars(body, bound, vars);
+            bound.pop();
+        }
+        Node::Expression(line) => line
+            .iter()
+            .flatten()
+            .for_each(|x| free_vars(x, bound, vars)),
+        Node::Variable(_)
+        | Node::Lhs(_)
+        | Node::Operator(_)
+        | Node::Literal(_)
+        | Node::Quantity(..)
+        | Node::Conversion(_) => (),
+    }
+}
+
+/// Returns the variable bound by a call to a builtin function which binds its
+/// first argument while evaluating its last one, e.g. `x` in
+/// `map(x, xs, x ^ 2)` or `k` in `sum(k, 1, 10, k ^ 2)`; `None` for any other
+/// call.
+pub(crate) fn binding<'a>(function: &str, args: &'a [Node]) -> Option<&'a str> {
+    if !matches!(
+        (function, args.len()),
+        ("map" | "filter", 3) | ("sum" | "prod", 4)
+    ) {
+        return None;
+    }
+    match &args[0] {
+        Node::Expression(line) => match line.as_slice() {
+            [Some(Node::Variable(var))] => Some(var),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -1,6 +1,12 @@
-use std::collections::HashMap;
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Index;
 
 use crate::ast::{Node, Operator};
+use crate::number;
+use crate::unit::{Quantity, Unit};
+use crate::value::Value;
 use crate::Integer;
 use crate::{
     error::Error,
@@ -14,6 +20,12 @@
 /// Create a blank context by calling `MathContext::new()`, add variables with
 /// `var_set()`, and evaluate expressions with `eval()`
 ///
+/// With the `serde` feature enabled the context's variables and last value
+/// can be serialized (integers are encoded as decimal strings, followed by
+/// their unit if they have one, and lists as arrays), temporary scopes from
+/// [`MathContext::with_scope()`] aren't included, and neither is the
+/// iteration limit.
+///
 /// # Examples
 /// ```
 /// use jnk::context::MathContext;
@@ -27,20 +39,108 @@
 /// );
 /// assert_eq!(
 ///     context.eval("myVar / 2").unwrap().value,
-///     jnk::Integer::from(21)
-/// );/
+///     Some(jnk::Integer::from(21))
+/// );
 /// assert_eq!(
 ///     context.eval("2 ^ 32 - 1").unwrap().value,
-///     jnk::Integer::from(u32::MAX)
+///     Some(jnk::Integer::from(u32::MAX))
 /// );
+///
+/// let size = context.eval("10 MiB / 2 s").unwrap();
+/// assert_eq!(size.value.unwrap(), 5);
+/// assert_eq!(size.unit.to_string(), "MiB/s");
 /// ```
 ///
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MathContext {
-    var_tab: HashMap<String, Integer>,
-    last: Integer,
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "vars", with = "crate::serde_int::map")
+    )]
+    var_tab: HashMap<String, Value>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    scopes: Vec<HashMap<String, Value>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_int::value"))]
+    last: Value,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip, default = "MathContext::default_max_iterations")
+    )]
+    max_iterations: usize,
+    /// Iterations used so far by the expression being evaluated.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    iterations: Cell<usize>,
 }
 
+impl Default for MathContext {
+    fn default() -> Self {
+        Self {
+            var_tab: HashMap::new(),
+            scopes: Vec::new(),
+            last: Value::default(),
+            max_iterations: Self::default_max_iterations(),
+            iterations: Cell::new(0),
+        }
+    }
+}
+
+/// Words reserved by the expression grammar, these can't be used as variable
+/// names.
+const KEYWORDS: &[&str] = &["let", "in", "to"];
+
+/// Names of the builtin functions which can be called in expressions, e.g.
+/// `powmod(3, 2 ^ 100, 7)`.
+///
+/// - `powmod(base, exp, mod)`: modular exponentiation
+/// - `isprime(n)` or `isprime(n, rounds)`: `1` if `n` (which can't be
+///   negative) is probably prime (using `rounds` rounds of Miller-Rabin, 30
+///   by default), otherwise `0`
+/// - `nextprime(n)`, `prevprime(n)`: the next prime after `n`, or the last
+///   one before it
+/// - `factor(n)`: the prime factors of `n` as a list of `[prime, exponent]`
+///   pairs (along with `[-1, 1]` if `n` is negative)
+/// - `totient(n)`: Euler's totient of `n`
+/// - `divisors(n)`: the positive divisors of `n`
+///
+///   `factor`, `totient` and `divisors` return [`Error::IterationLimit`] if
+///   factoring `n` takes more than [`MathContext::max_iterations()`] steps
+/// - `jacobi(a, n)`, `legendre(a, p)`, `kronecker(a, n)`: the Jacobi,
+///   Legendre and Kronecker symbols
+/// - `egcd(a, b)`: `[g, s, t]`, the gcd of `a` and `b` and Bezout
+///   coefficients such that `a * s + b * t == g`
+/// - `len(xs)`, `sum(xs)`, `prod(xs)`: the length, sum and product of a list
+/// - `sum(k, a, b, expr)`, `prod(k, a, b, expr)`: the sum and product of
+///   `expr` evaluated with `k` bound to each integer from `a` to `b`
+///   (inclusive), e.g. `sum(k, 1, 100, k ^ 2)`
+/// - `min(xs)`, `max(xs)`: the smallest and largest number in a list (or of
+///   the arguments, e.g. `min(a, b)`)
+/// - `sort(xs)`, `reverse(xs)`: the list sorted, or in reverse order
+/// - `map(x, xs, expr)`, `filter(x, xs, cond)`: `expr` evaluated with `x`
+///   bound to each item of `xs`, or the items for which `cond` is non-zero
+pub const FUNCTIONS: &[&str] = &[
+    "powmod",
+    "isprime",
+    "nextprime",
+    "prevprime",
+    "factor",
+    "totient",
+    "divisors",
+    "jacobi",
+    "legendre",
+    "kronecker",
+    "egcd",
+    "len",
+    "sum",
+    "prod",
+    "min",
+    "max",
+    "sort",
+    "reverse",
+    "map",
+    "filter",
+];
+
 impl MathContext {
     /// Creates a new math context, with a blank variable table
     #[inline]
@@ -48,18 +148,49 @@ pub fn new() -> Self {
         MathContext::default()
     }
 
-    /// Returns the result of the last (non-discarded) expression evaluated (initially zero)
+    /// Returns the result of the last (non-discarded) expression evaluated (initially zero),
+    /// or `None` if it was a list, see [`Self::last_value()`].
+    #[inline]
+    pub fn last(&self) -> Option<&Integer> {
+        self.last.as_number().map(|x| &x.value)
+    }
+
+    /// Same as [`Self::last()`], for any value (including lists).
     #[inline]
-    pub fn last(&self) -> &Integer {
+    pub fn last_value(&self) -> &Value {
         &self.last
     }
 
+    fn default_max_iterations() -> usize {
+        1_000_000
+    }
+
+    /// Returns the maximum number of iterations an expression can take, one
+    /// million by default. Every term of a sum or product (e.g.
+    /// `sum(k, 1, n, k ^ 2)`), every item of a range (e.g. `1..n`) and every
+    /// step of a factorization (e.g. `factor(n)`) counts towards it, so a sum
+    /// nested in another one uses up to the product of their lengths.
+    #[inline]
+    pub fn max_iterations(&self) -> usize {
+        self.max_iterations
+    }
+
+    /// Sets the iteration limit, see [`Self::max_iterations()`]; expressions
+    /// exceeding it return [`Error::IterationLimit`] (a sum or range is
+    /// checked before any of its terms are evaluated).
+    #[inline]
+    pub fn set_max_iterations(&mut self, max: usize) {
+        self.max_iterations = max
+    }
+
     /// Checks if `name` is a valid context variable name (ascii alphanumeric,
-    /// and starts with a letter); used internally in [`Self::var_set()`].
+    /// starts with a letter, and isn't a keyword like `let`); used internally
+    /// in [`Self::var_set()`].
     #[inline]
     pub fn var_valid(name: &str) -> bool {
         (name.starts_with(|x: char| x.is_ascii_alphabetic())
-            && name.chars().all(|x| x.is_ascii_alphanumeric()))
+            && name.chars().all(|x| x.is_ascii_alphanumeric())
+            && !KEYWORDS.contains(&name))
             || name == "_"
     }
 
@@ -70,10 +201,20 @@ pub fn var_valid(name: &str) -> bool {
     /// variable "`_`" which always refers to the last returned value (so values
     /// assigned to it are effectively disregarded).
     pub fn var_set(&mut self, name: String, value: Integer) -> Result<(), Error> {
-        if Self::var_valid(&name) {
-            self.var_tab.insert(name, value);
+        self.var_set_quantity(name, value.into())
+    }
+
+    /// Same as [`Self::var_set()`], for a value with a unit.
+    pub fn var_set_quantity(&mut self, name: String, value: Quantity) -> Result<(), Error> {
+        self.var_set_value(name, value.into())
+    }
+
+    /// Same as [`Self::var_set()`], for any value (including lists).
+    pub fn var_set_value(&mut self, name: String, value: Value) -> Result<(), Error> {
+        if name == "_" {
             Ok(())
-        } else if name == "_" {
+        } else if Self::var_valid(&name) {
+            self.vars_mut().insert(name, value);
             Ok(())
         } else {
             Err(Error::NotValidVar(name))
@@ -84,13 +225,131 @@ pub fn var_set(&mut self, name: String, value: Integer) -> Result<(), Error> {
     /// name doesn't exist in the context (this includes invalid names). the
     /// specaial variable "`_`" returns the last returned value, although this
     /// is primairly to match the syntax of [`MathContext::eval()`], it's better
-    /// to just call [`MathContext::last()`] directly.
+    /// to just call [`MathContext::last()`] directly. Variables holding lists
+    /// aren't returned, see [`Self::var_get_value()`].
     pub fn var_get(&self, name: &str) -> Option<&Integer> {
+        self.var_get_quantity(name).map(|x| &x.value)
+    }
+
+    /// Same as [`Self::var_get()`], including the variable's unit.
+    pub fn var_get_quantity(&self, name: &str) -> Option<&Quantity> {
+        self.var_get_value(name).and_then(Value::as_number)
+    }
+
+    /// Same as [`Self::var_get()`], for any value (including lists).
+    pub fn var_get_value(&self, name: &str) -> Option<&Value> {
         if name == "_" {
-            Some(self.last())
+            Some(&self.last)
         } else {
-            self.var_tab.get(name)
+            self.scopes
+                .iter()
+                .rev()
+                .find_map(|scope| scope.get(name))
+                .or_else(|| self.var_tab.get(name))
+        }
+    }
+
+    /// Checks if a variable exists in the context, see [`Self::var_get_value()`].
+    #[inline]
+    pub fn var_contains(&self, name: &str) -> bool {
+        self.var_get_value(name).is_some()
+    }
+
+    /// Removes a variable from the context, returning its value if it
+    /// existed. Inside of [`Self::with_scope()`] only the innermost scope is
+    /// affected.
+    pub fn var_remove(&mut self, name: &str) -> Option<Value> {
+        self.vars_mut().remove(name)
+    }
+
+    /// Removes all variables from the context, inside of
+    /// [`Self::with_scope()`] only the innermost scope is cleared.
+    pub fn var_clear(&mut self) {
+        self.vars_mut().clear()
+    }
+
+    /// Returns an iterator over all the variables visible in the context
+    /// (including scoped ones) as name/value pairs, sorted by name. Variables
+    /// holding lists are skipped, see [`Self::vars_value()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use jnk::context::MathContext;
+    ///
+    /// let mut context = MathContext::new();
+    /// context.eval("b = 2").unwrap();
+    /// context.eval("a = 1").unwrap();
+    ///
+    /// let names = context.vars().map(|(name, _)| name).collect::<Vec<_>>();
+    /// assert_eq!(names, ["a", "b"]);
+    /// ```
+    pub fn vars(&self) -> impl Iterator<Item = (&str, &Integer)> {
+        self.vars_quantity().map(|(k, v)| (k, &v.value))
+    }
+
+    /// Same as [`Self::vars()`], including each variable's unit.
+    pub fn vars_quantity(&self) -> impl Iterator<Item = (&str, &Quantity)> {
+        self.vars_value()
+            .filter_map(|(k, v)| Some((k, v.as_number()?)))
+    }
+
+    /// Same as [`Self::vars()`], for every value (including lists).
+    pub fn vars_value(&self) -> impl Iterator<Item = (&str, &Value)> {
+        std::iter::once(&self.var_tab)
+            .chain(&self.scopes)
+            .flatten()
+            .map(|(k, v)| (k.as_str(), v))
+            .collect::<BTreeMap<_, _>>()
+            .into_iter()
+    }
+
+    /// Adds every variable from `iter` to the context, see [`Self::var_set()`].
+    /// If any of the names are invalid [`Error::NotValidVar`] is returned and
+    /// the context is left unchanged.
+    pub fn extend<I>(&mut self, iter: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = (String, Integer)>,
+    {
+        let vars = iter.into_iter().collect::<Vec<_>>();
+        if let Some((name, _)) = vars.iter().find(|(name, _)| !Self::var_valid(name)) {
+            return Err(Error::NotValidVar(name.to_owned()));
         }
+        vars.into_iter()
+            .try_for_each(|(name, value)| self.var_set(name, value))
+    }
+
+    /// Returns the variable table assignments currently go to.
+    fn vars_mut(&mut self) -> &mut HashMap<String, Value> {
+        self.scopes.last_mut().unwrap_or(&mut self.var_tab)
+    }
+
+    /// Runs `f` with a new temporary scope layered over the variable table,
+    /// variables assigned inside `f` (through [`Self::var_set()`] or
+    /// [`Self::eval()`]) shadow existing ones and are discarded when `f`
+    /// returns, along with any change to [`Self::last()`].
+    ///
+    /// # Examples
+    /// ```
+    /// use jnk::context::MathContext;
+    ///
+    /// let mut context = MathContext::new();
+    /// context.eval("r = 2").unwrap();
+    ///
+    /// let area = context.with_scope(|scoped| {
+    ///     scoped.eval("r = 5").unwrap();
+    ///     scoped.eval("3 * r ^ 2").unwrap().value.unwrap()
+    /// });
+    ///
+    /// assert_eq!(area, 75);
+    /// assert_eq!(*context.var_get("r").unwrap(), 2);
+    /// ```
+    pub fn with_scope<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let last = self.last.clone();
+        self.scopes.push(HashMap::new());
+        let res = f(self);
+        self.scopes.pop();
+        self.last = last;
+        res
     }
 
     /// Evaluate a math expression, this will update the last result value,
@@ -102,62 +361,86 @@ pub fn eval(&mut self, expr: &str) -> Result<Eval, Error> {
             if lhs == "_" {
                 res.var = None
             } else {
-                self.var_set(lhs.to_owned(), res.value.to_owned())?;
+                self.var_set_value(lhs.to_owned(), res.result())?;
             }
         }
-        self.last = res.value.clone();
+        self.last = res.result();
         Ok(res)
     }
 
     /// Evaluate a math expression, and disregard the result, this will **not**
     /// update the last result value, and the left hand variable will be
-    /// disregarded (if it exists)
+    /// disregarded (if it exists), the value's unit is discarded too.
+    /// Returns [`Error::ExpectedNumber`] if the expression evaluates to a list.
     pub fn eval_disregard(&self, expr: &str) -> Result<Integer, Error> {
-        let res = self.eval_internal(expr)?;
-        Ok(res.value)
+        self.eval_internal(expr)?.value.ok_or(Error::ExpectedNumber)
     }
 
     fn eval_internal(&self, expr: &str) -> Result<Eval, Error> {
-        let mut pairs = match MathParser::parse(Rule::Main, expr) {
-            Ok(x) => x,
-            Err(e) => {
-                return Err(Error::ParseError {
-                    line: expr.to_owned(),
-                    parse_failure: Box::new(e),
-                })
-            }
-        };
-
-        let ast = crate::ast::create_ast(
-            pairs
-                .next()
-                .ok_or_else(|| unreachable!("Rule::Main can't be empty"))?,
-        );
-
-        if let Node::Main(lhs, mut expr) = ast {
-            let var = lhs.map(|x| match *x {
-                Node::Lhs(x) => x,
-                _ => unreachable!(),
-            });
-            let value = self.eval_ast(&mut expr)?;
-            Ok(Eval { var, value })
-        } else {
-            unreachable!("AST generated from Rule::Main must have root level Main node")
-        }
+        let (var, mut expr) = parse(expr)?;
+        self.iterations.set(0);
+        let value = self.eval_ast(&mut expr)?;
+        Ok(Eval::new(value, var))
+    }
+
+    /// Finds which variables an expression reads and assigns without
+    /// evaluating it, returns an error only if the expression can't be parsed.
+    ///
+    /// # Examples
+    /// ```
+    /// use jnk::context::MathContext;
+    ///
+    /// let analysis = MathContext::analyze("area = let r = x in 3 * r ^ y").unwrap();
+    /// assert_eq!(analysis.var.as_deref(), Some("area"));
+    /// assert_eq!(analysis.reads, ["x", "y"]);
+    /// ```
+    pub fn analyze(expr: &str) -> Result<Analysis, Error> {
+        let (var, expr) = parse(expr)?;
+        let mut reads = Vec::new();
+        crate::ast::free_vars(&expr, &mut Vec::new(), &mut reads);
+        Ok(Analysis { var, reads })
     }
 
-    fn eval_ast(&self, ast: &mut Node) -> Result<Integer, Error> {
+    fn eval_ast(&self, ast: &mut Node) -> Result<Value, Error> {
         match ast {
             Node::Main(_, expr) => self.eval_ast(expr),
-            Node::Variable(var) => match self.var_get(var) {
+            Node::Variable(var) => match self.var_get_value(var) {
                 Some(x) => Ok(x.clone()),
                 None => Err(Error::VarNotFound(var.clone())),
             },
             Node::Lhs(_) => Err(Error::InternalAstFailure),
             Node::Operator(_) => Err(Error::InternalAstFailure),
             Node::Parenthetical(inner) => self.eval_ast(inner),
-            Node::Negation(inner) => Ok(-(self.eval_ast(inner)?)),
+            Node::Negation(inner) => self
+                .eval_ast(inner)?
+                .map(&mut |x| Ok(Quantity::new(-x.value, x.unit))),
             Node::Literal(x) => Ok(x.clone()),
+            Node::Quantity(value, unit) => Ok(Quantity::new(value.clone(), unit.parse()?).into()),
+            Node::Conversion(_) => Err(Error::InternalAstFailure),
+            Node::Function(name, args) => self.eval_function(name, args),
+            Node::List(items) => items
+                .iter_mut()
+                .map(|x| self.eval_ast(x))
+                .collect::<Result<_, _>>()
+                .map(Value::List),
+            Node::Index(inner, index) => {
+                let values = self.eval_ast(inner)?.into_list()?;
+                let index = self.number(index)?.plain()?;
+                let len = values.len();
+                // negative indices count from the end of the list
+                let i = match index < 0 {
+                    true => Integer::from(&index + len),
+                    false => index.clone(),
+                };
+                i.to_usize()
+                    .and_then(|i| values.into_iter().nth(i))
+                    .ok_or(Error::IndexOutOfBounds(index, len))
+            }
+            Node::Let(name, value, body) => {
+                let value = self.eval_ast(value)?;
+                crate::ast::substitute(body, name, &value);
+                self.eval_ast(body)
+            }
             Node::Expression(line) => {
                 // Parenthesizes
                 // clippy is telling me to just use a standard iterator loop,
@@ -171,6 +454,12 @@ fn eval_ast(&self, ast: &mut Node) -> Result<Integer, Error> {
                     } else if let Some(Node::Negation(_)) = line[i] {
                         let mut node = line[i].take().ok_or(Error::InternalAstFailure)?;
                         line[i] = Some(Node::Literal(self.eval_ast(&mut node)?))
+                    } else if let Some(
+                        Node::Function(..) | Node::Let(..) | Node::List(_) | Node::Index(..),
+                    ) = line[i]
+                    {
+                        let mut node = line[i].take().ok_or(Error::InternalAstFailure)?;
+                        line[i] = Some(Node::Literal(self.eval_ast(&mut node)?))
                     }
                 }
 
@@ -179,20 +468,18 @@ fn eval_ast(&self, ast: &mut Node) -> Result<Integer, Error> {
                     if let Some(Node::Operator(Operator::Exponent)) = line[i] {
                         let lhs = self.eval_ast(&mut node_left(line, i)?)?;
                         let rhs = self.eval_ast(&mut node_right(line, i)?)?;
-
-                        let val: Integer;
-                        if rhs < 0 {
-                            match rhs.as_neg().to_u32() {
-                                Some(x) => val = Integer::from(1) / lhs.pow(x),
-                                None => return Err(Error::ExponentOverflow(rhs)),
-                            }
-                        } else {
-                            match rhs.to_u32() {
-                                Some(x) => val = lhs.pow(x),
-                                None => return Err(Error::ExponentOverflow(rhs)),
-                            }
-                        }
-                        line[i] = Some(Node::Literal(val));
+                        let value = lhs.broadcast(rhs, &mut |lhs, rhs| {
+                            let rhs = rhs.plain()?;
+                            let unit = match lhs.unit.is_empty() {
+                                true => Unit::new(),
+                                false => lhs.unit.pow(
+                                    rhs.to_i32()
+                                        .ok_or_else(|| Error::ExponentOverflow(rhs.clone()))?,
+                                ),
+                            };
+                            Ok(Quantity::new(pow(lhs.value, rhs)?, unit))
+                        })?;
+                        line[i] = Some(Node::Literal(value));
                     }
                 }
 
@@ -202,11 +489,20 @@ fn eval_ast(&self, ast: &mut Node) -> Result<Integer, Error> {
                         if let Operator::Multiplication = op {
                             let lhs = self.eval_ast(&mut node_left(line, i)?)?;
                             let rhs = self.eval_ast(&mut node_right(line, i)?)?;
-                            line[i] = Some(Node::Literal(lhs * rhs));
+                            line[i] = Some(Node::Literal(lhs.broadcast(rhs, &mut mul)?));
                         } else if let Operator::Division = op {
                             let lhs = self.eval_ast(&mut node_left(line, i)?)?;
                             let rhs = self.eval_ast(&mut node_right(line, i)?)?;
-                            line[i] = Some(Node::Literal(lhs / rhs));
+                            let value = lhs.broadcast(rhs, &mut |lhs, rhs| {
+                                if rhs.value == 0 {
+                                    return Err(Error::DivisionByZero);
+                                }
+                                Ok(Quantity::new(
+                                    lhs.value / rhs.value,
+                                    lhs.unit.div(&rhs.unit),
+                                ))
+                            })?;
+                            line[i] = Some(Node::Literal(value));
                         }
                     }
                 }
@@ -217,24 +513,92 @@ fn eval_ast(&self, ast: &mut Node) -> Result<Integer, Error> {
                         if let Operator::Addition = op {
                             let lhs = self.eval_ast(&mut node_left(line, i)?)?;
                             let rhs = self.eval_ast(&mut node_right(line, i)?)?;
-                            line[i] = Some(Node::Literal(lhs + rhs));
+                            line[i] = Some(Node::Literal(lhs.broadcast(rhs, &mut add)?));
                         } else if let Operator::Subtraction = op {
                             let lhs = self.eval_ast(&mut node_left(line, i)?)?;
                             let rhs = self.eval_ast(&mut node_right(line, i)?)?;
-                            line[i] = Some(Node::Literal(lhs - rhs));
+                            let value = lhs.broadcast(rhs, &mut |lhs, rhs| {
+                                let (lhs, rhs, unit) = lhs.align(rhs)?;
+                                Ok(Quantity::new(lhs - rhs, unit))
+                            })?;
+                            line[i] = Some(Node::Literal(value));
+                        }
+                    }
+                }
+
+                // Ranges (`a..b` excludes `b`, `a..=b` includes it)
+                for i in 0..line.len() {
+                    if let Some(Node::Operator(op @ (Operator::Range | Operator::RangeInclusive))) =
+                        line[i]
+                    {
+                        let start = self.number(&mut node_left(line, i)?)?.plain()?;
+                        let mut end = self.number(&mut node_right(line, i)?)?.plain()?;
+                        if let Operator::RangeInclusive = op {
+                            end += 1;
                         }
+                        self.check_iterations(&start, &end)?;
+                        let mut values = Vec::new();
+                        let mut x = start;
+                        while x < end {
+                            values.push(Value::from(x.clone()));
+                            x += 1;
+                        }
+                        line[i] = Some(Node::Literal(Value::List(values)));
+                    }
+                }
+
+                // Comparisons
+                for i in 0..line.len() {
+                    if let Some(Node::Operator(op)) = line[i] {
+                        let cmp = match op {
+                            Operator::Equal => Ordering::is_eq,
+                            Operator::NotEqual => Ordering::is_ne,
+                            Operator::Less => Ordering::is_lt,
+                            Operator::LessEqual => Ordering::is_le,
+                            Operator::Greater => Ordering::is_gt,
+                            Operator::GreaterEqual => Ordering::is_ge,
+                            _ => continue,
+                        };
+                        let lhs = self.eval_ast(&mut node_left(line, i)?)?;
+                        let rhs = self.eval_ast(&mut node_right(line, i)?)?;
+                        // two lists are equal or not as a whole, anything
+                        // else is compared item by item
+                        let value = match (op, &lhs, &rhs) {
+                            (
+                                Operator::Equal | Operator::NotEqual,
+                                Value::List(_),
+                                Value::List(_),
+                            ) => {
+                                let equal = lhs.equals(&rhs)?;
+                                Integer::from((equal == matches!(op, Operator::Equal)) as u8).into()
+                            }
+                            _ => lhs.broadcast(rhs, &mut |lhs, rhs| {
+                                Ok(Integer::from(cmp(lhs.compare(&rhs)?) as u8).into())
+                            })?,
+                        };
+                        line[i] = Some(Node::Literal(value));
                     }
                 }
 
+                // Conversions (`to` can only end an expression)
+                if let Some(Node::Conversion(unit)) = line.last().and_then(|x| x.as_ref()) {
+                    let unit = unit.parse::<Unit>()?;
+                    let i = line.len() - 1;
+                    let value = self.eval_ast(&mut node_left(line, i)?)?.map(&mut |x| {
+                        let value = x.unit.convert(x.value, &unit)?;
+                        Ok(Quantity::new(value, unit.clone()))
+                    })?;
+                    line[i] = Some(Node::Literal(value));
+                }
+
                 // as mentioned above i couldn't get a normal iterator loop to
                 // work properly with the borrow checker, so I'm just telling
                 // clippy to stfu
                 #[allow(clippy::needless_range_loop)]
                 for i in 0..line.len() {
-                    if let Some(Node::Variable(x)) = &line[i] {
-                        line[i] = Some(Node::Literal(
-                            self.eval_ast(&mut Node::Variable(x.to_owned()))?,
-                        ));
+                    if let Some(Node::Variable(_) | Node::Quantity(..)) = &line[i] {
+                        let mut node = line[i].take().ok_or(Error::InternalAstFailure)?;
+                        line[i] = Some(Node::Literal(self.eval_ast(&mut node)?));
                     }
                 }
 
@@ -255,14 +619,388 @@ fn eval_ast(&self, ast: &mut Node) -> Result<Integer, Error> {
             }
         }
     }
+
+    /// Evaluates `node`, returning [`Error::ExpectedNumber`] if it's a list.
+    fn number(&self, node: &mut Node) -> Result<Quantity, Error> {
+        self.eval_ast(node)?.into_number()
+    }
+
+    fn eval_function(&self, name: &str, args: &mut [Node]) -> Result<Value, Error> {
+        match name {
+            "powmod" => {
+                let [base, exp, modulus] = self.eval_args::<3>(name, args)?;
+                if modulus == 0 {
+                    return Err(Error::DivisionByZero);
+                }
+                base.pow_mod(&exp, &modulus)
+                    .map(Value::from)
+                    .map_err(|base| Error::NoInverse(base, modulus))
+            }
+            "isprime" => {
+                let (n, rounds) = match args.len() {
+                    1 => {
+                        let [n] = self.eval_args::<1>(name, args)?;
+                        (n, number::PRIME_ROUNDS)
+                    }
+                    _ => {
+                        let [n, rounds] = self.eval_args::<2>(name, args)?;
+                        let rounds = rounds.to_u32().filter(|x| *x > 0).ok_or_else(|| {
+                            Error::InvalidArgument(name.to_owned(), rounds.clone())
+                        })?;
+                        (n, rounds)
+                    }
+                };
+                if n < 0 {
+                    return Err(Error::InvalidArgument(name.to_owned(), n));
+                }
+                Ok(Integer::from(number::is_prime(&n, rounds) as u8).into())
+            }
+            "nextprime" => {
+                let [n] = self.eval_args::<1>(name, args)?;
+                Ok(n.next_prime().into())
+            }
+            "prevprime" => {
+                let [n] = self.eval_args::<1>(name, args)?;
+                if n <= 2 {
+                    return Err(Error::InvalidArgument(name.to_owned(), n));
+                }
+                Ok(n.prev_prime().into())
+            }
+            "factor" => {
+                let [n] = self.eval_args::<1>(name, args)?;
+                if n == 0 {
+                    return Err(Error::InvalidArgument(name.to_owned(), n));
+                }
+                let sign = (n < 0).then(|| (Integer::from(-1), 1));
+                let factors = sign
+                    .into_iter()
+                    .chain(self.with_budget(|budget| number::factor(&n.abs(), budget))?)
+                    .map(|(p, e)| Value::List(vec![p.into(), Integer::from(e).into()]));
+                Ok(Value::List(factors.collect()))
+            }
+            "totient" => {
+                let [n] = self.eval_args::<1>(name, args)?;
+                if n <= 0 {
+                    return Err(Error::InvalidArgument(name.to_owned(), n));
+                }
+                Ok(self
+                    .with_budget(|budget| number::totient(&n, budget))?
+                    .into())
+            }
+            "divisors" => {
+                let [n] = self.eval_args::<1>(name, args)?;
+                if n == 0 {
+                    return Err(Error::InvalidArgument(name.to_owned(), n));
+                }
+                let divisors = self.with_budget(|budget| number::divisors(&n.abs(), budget))?;
+                let divisors = divisors.into_iter().map(Value::from);
+                Ok(Value::List(divisors.collect()))
+            }
+            "jacobi" => {
+                let [a, n] = self.eval_args::<2>(name, args)?;
+                if n <= 0 || n.is_even() {
+                    return Err(Error::InvalidArgument(name.to_owned(), n));
+                }
+                Ok(Integer::from(a.jacobi(&n)).into())
+            }
+            "legendre" => {
+                let [a, p] = self.eval_args::<2>(name, args)?;
+                if p <= 2 || !number::is_prime(&p, number::PRIME_ROUNDS) {
+                    return Err(Error::InvalidArgument(name.to_owned(), p));
+                }
+                Ok(Integer::from(a.legendre(&p)).into())
+            }
+            "kronecker" => {
+                let [a, n] = self.eval_args::<2>(name, args)?;
+                Ok(Integer::from(a.kronecker(&n)).into())
+            }
+            "len" => Ok(Integer::from(self.list_arg(name, args)?.len()).into()),
+            "sum" | "prod" if args.len() == 4 => {
+                let var = crate::ast::binding(name, args)
+                    .ok_or_else(|| Error::ExpectedVariable(name.to_owned()))?
+                    .to_owned();
+                let start = self.number(&mut args[1])?.plain()?;
+                let end = self.number(&mut args[2])?.plain()? + 1;
+                self.check_iterations(&start, &end)?;
+                // the body is evaluated once per index, so a range is never
+                // built
+                let mut k = start;
+                let terms = std::iter::from_fn(|| {
+                    if k >= end {
+                        return None;
+                    }
+                    let mut body = args[3].clone();
+                    crate::ast::substitute(&mut body, &var, &Value::from(k.clone()));
+                    k += 1;
+                    Some(self.eval_ast(&mut body))
+                });
+                accumulate(name, terms)
+            }
+            "sum" | "prod" => {
+                let values = self.list_arg(name, args)?;
+                accumulate(name, values.into_iter().map(Ok))
+            }
+            "min" | "max" => {
+                // either a single list, or the values to compare as arguments
+                let values = match args.len() {
+                    1 => self.list_arg(name, args)?,
+                    _ => args
+                        .iter_mut()
+                        .map(|x| self.eval_ast(x))
+                        .collect::<Result<_, _>>()?,
+                };
+                let mut values = values.into_iter().map(Value::into_number);
+                let mut best = values
+                    .next()
+                    .ok_or_else(|| Error::EmptyList(name.to_owned()))??;
+                for x in values {
+                    let x = x?;
+                    let ord = x.compare(&best)?;
+                    if (name == "min" && ord.is_lt()) || (name == "max" && ord.is_gt()) {
+                        best = x;
+                    }
+                }
+                Ok(best.into())
+            }
+            "sort" => {
+                let mut values = self
+                    .list_arg(name, args)?
+                    .into_iter()
+                    .map(Value::into_number)
+                    .collect::<Result<Vec<_>, _>>()?;
+                if let Some(first) = values.first() {
+                    if let Some(x) = values.iter().find(|x| !x.unit.compatible(&first.unit)) {
+                        return Err(Error::DimensionMismatch(first.unit.clone(), x.unit.clone()));
+                    }
+                }
+                values.sort_by(|a, b| match a.compare(b) {
+                    Ok(x) => x,
+                    Err(_) => unreachable!("units checked above"),
+                });
+                Ok(Value::List(values.into_iter().map(Value::from).collect()))
+            }
+            "reverse" => {
+                let mut values = self.list_arg(name, args)?;
+                values.reverse();
+                Ok(Value::List(values))
+            }
+            "map" | "filter" => {
+                if args.len() != 3 {
+                    return Err(Error::ArgumentCount {
+                        name: name.to_owned(),
+                        expected: 3,
+                        found: args.len(),
+                    });
+                }
+                let var = crate::ast::binding(name, args)
+                    .ok_or_else(|| Error::ExpectedVariable(name.to_owned()))?
+                    .to_owned();
+                let values = self.eval_ast(&mut args[1])?.into_list()?;
+                let mut result = Vec::with_capacity(values.len());
+                for x in values {
+                    let mut body = args[2].clone();
+                    crate::ast::substitute(&mut body, &var, &x);
+                    let value = self.eval_ast(&mut body)?;
+                    if name == "map" {
+                        result.push(value);
+                    } else if value.into_number()?.value != 0 {
+                        result.push(x);
+                    }
+                }
+                Ok(Value::List(result))
+            }
+            "egcd" => {
+                let [a, b] = self.eval_args::<2>(name, args)?;
+                let (g, s, t) = a.extended_gcd(b, Integer::new());
+                Ok(Value::List(vec![g.into(), s.into(), t.into()]))
+            }
+            _ => Err(Error::FunctionNotFound(name.to_owned())),
+        }
+    }
+
+    /// Takes the steps of counting from `start` up to (but not including)
+    /// `end` from the iterations left, see [`Self::with_budget()`].
+    fn check_iterations(&self, start: &Integer, end: &Integer) -> Result<(), Error> {
+        self.with_budget(|budget| {
+            let steps = Integer::from(end - start).max(Integer::ZERO).to_usize()?;
+            *budget = budget.checked_sub(steps)?;
+            Some(())
+        })
+    }
+
+    /// Runs `f` with the iterations the expression being evaluated has left
+    /// (out of [`Self::max_iterations()`]), keeping those it takes. Returns
+    /// [`Error::IterationLimit`] if `f` returns `None`, when it ran out.
+    fn with_budget<T>(&self, f: impl FnOnce(&mut usize) -> Option<T>) -> Result<T, Error> {
+        let mut budget = self.max_iterations.saturating_sub(self.iterations.get());
+        let res = f(&mut budget);
+        self.iterations.set(self.max_iterations - budget);
+        res.ok_or(Error::IterationLimit(self.max_iterations))
+    }
+
+    /// Evaluates the single list argument of a function.
+    fn list_arg(&self, name: &str, args: &mut [Node]) -> Result<Vec<Value>, Error> {
+        if args.len() != 1 {
+            return Err(Error::ArgumentCount {
+                name: name.to_owned(),
+                expected: 1,
+                found: args.len(),
+            });
+        }
+        self.eval_ast(&mut args[0])?.into_list()
+    }
+
+    fn eval_args<const N: usize>(
+        &self,
+        name: &str,
+        args: &mut [Node],
+    ) -> Result<[Integer; N], Error> {
+        // functions only take plain numbers
+        if args.len() != N {
+            return Err(Error::ArgumentCount {
+                name: name.to_owned(),
+                expected: N,
+                found: args.len(),
+            });
+        }
+        let mut values = Vec::with_capacity(N);
+        for arg in args.iter_mut() {
+            values.push(self.number(arg)?.plain()?);
+        }
+        values.try_into().map_err(|_| Error::InternalAstFailure)
+    }
+}
+
+impl Index<&str> for MathContext {
+    type Output = Integer;
+
+    /// Retrieves a variable from the context, see [`MathContext::var_get()`].
+    ///
+    /// # Panics
+    /// Panics if the variable doesn't exist in the context.
+    fn index(&self, name: &str) -> &Integer {
+        self.var_get(name)
+            .unwrap_or_else(|| panic!("'{name}' not in variable table"))
+    }
+}
+
+/// The variables used by an expression, as returned by
+/// [`MathContext::analyze()`].
+#[derive(Debug, Clone, Default)]
+pub struct Analysis {
+    /// The variable the expression assigns to (if any)
+    pub var: Option<String>,
+    /// Every variable read by the expression in the order they appear,
+    /// excluding ones bound by `let`
+    pub reads: Vec<String>,
 }
 
+/// The result of an expression evaluated by a [`MathContext`], `var` is the
+/// variable the value was assigned to (if any).
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Eval {
-    pub value: Integer,
+    /// The value of the expression, `None` if it's a list (see `list`)
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            with = "crate::serde_int::option"
+        )
+    )]
+    pub value: Option<Integer>,
+    /// The unit of `value`, empty for plain numbers and lists
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            skip_serializing_if = "Unit::is_empty",
+            with = "crate::serde_int::unit"
+        )
+    )]
+    pub unit: Unit,
+    /// The items of the list the expression evaluated to, if it did
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            with = "crate::serde_int::list"
+        )
+    )]
+    pub list: Option<Vec<Value>>,
     pub var: Option<String>,
 }
 
+impl Eval {
+    fn new(value: Value, var: Option<String>) -> Self {
+        match value {
+            Value::Number(Quantity { value, unit }) => Self {
+                value: Some(value),
+                unit,
+                list: None,
+                var,
+            },
+            Value::List(list) => Self {
+                value: None,
+                unit: Unit::new(),
+                list: Some(list),
+                var,
+            },
+        }
+    }
+
+    /// Returns the result as a [`Value`], either the number (with its unit)
+    /// or the list.
+    ///
+    /// # Examples
+    /// ```
+    /// use jnk::context::MathContext;
+    ///
+    /// let mut context = MathContext::new();
+    /// let result = context.eval("[1, 2] * 3 km").unwrap();
+    /// assert_eq!(result.result().to_string(), "[3 km, 6 km]");
+    /// assert_eq!(context.eval("90 km").unwrap().result().to_string(), "90 km");
+    /// ```
+    pub fn result(&self) -> Value {
+        match &self.value {
+            Some(x) => Quantity::new(x.clone(), self.unit.clone()).into(),
+            None => Value::List(self.list.clone().unwrap_or_default()),
+        }
+    }
+}
+
+/// Parses an expression into the variable it's assigned to (if any) and its
+/// AST.
+fn parse(expr: &str) -> Result<(Option<String>, Box<Node>), Error> {
+    let mut pairs = match MathParser::parse(Rule::Main, expr) {
+        Ok(x) => x,
+        Err(e) => {
+            return Err(Error::ParseError {
+                line: expr.to_owned(),
+                parse_failure: Box::new(e),
+            })
+        }
+    };
+
+    let ast = crate::ast::create_ast(
+        pairs
+            .next()
+            .ok_or_else(|| unreachable!("Rule::Main can't be empty"))?,
+    );
+
+    if let Node::Main(lhs, expr) = ast {
+        let var = lhs.map(|x| match *x {
+            Node::Lhs(x) => x,
+            _ => unreachable!(),
+        });
+        Ok((var, expr))
+    } else {
+        unreachable!("AST generated from Rule::Main must have root level Main node")
+    }
+}
+
 fn node_left<T>(line: &mut [Option<T>], mut i: usize) -> Result<T, Error> {
     Ok(loop {
         if i == 0 {
@@ -286,3 +1024,62 @@ fn node_right<T>(line: &mut [Option<T>], mut i: usize) -> Result<T, Error> {
         }
     })
 }
+
+/// Raises `base` to the power of `exp`; exponents outside of `u32` are only
+/// supported when the result doesn't depend on their magnitude (bases of `0`,
+/// `1` and `-1`), negative exponents always truncate towards zero.
+fn pow(base: Integer, exp: Integer) -> Result<Integer, Error> {
+    if exp < 0 {
+        if base == 0 {
+            Err(Error::DivisionByZero)
+        } else if base == 1 {
+            Ok(base)
+        } else if base == -1 {
+            Ok(if exp.is_even() {
+                Integer::from(1)
+            } else {
+                base
+            })
+        } else {
+            // |base| > 1, so 1 / base^x always truncates to zero
+            Ok(Integer::new())
+        }
+    } else {
+        match exp.to_u32() {
+            Some(x) => Ok(base.pow(x)),
+            None if base == 0 || base == 1 => Ok(base),
+            None if base == -1 => Ok(if exp.is_even() {
+                Integer::from(1)
+            } else {
+                base
+            }),
+            None => Err(Error::ExponentOverflow(exp)),
+        }
+    }
+}
+
+/// Sums (or multiplies, for `prod`) `values`, returning the first error
+/// encountered; the empty sum is `0` and the empty product `1`.
+fn accumulate<I>(name: &str, mut values: I) -> Result<Value, Error>
+where
+    I: Iterator<Item = Result<Value, Error>>,
+{
+    type Op = fn(Quantity, Quantity) -> Result<Quantity, Error>;
+    let mut f: Op = if name == "sum" { add } else { mul };
+    let Some(first) = values.next() else {
+        return Ok(Integer::from((name == "prod") as u8).into());
+    };
+    values.try_fold(first?, |acc, x| acc.broadcast(x?, &mut f))
+}
+
+/// Adds two numbers, converting them to a common unit, see [`Quantity::align()`].
+fn add(lhs: Quantity, rhs: Quantity) -> Result<Quantity, Error> {
+    let (lhs, rhs, unit) = lhs.align(rhs)?;
+    Ok(Quantity::new(lhs + rhs, unit))
+}
+
+/// Multiplies two numbers, along with their units.
+fn mul(lhs: Quantity, rhs: Quantity) -> Result<Quantity, Error> {
+    let unit = lhs.unit.mul(&rhs.unit);
+    Ok(Quantity::new(lhs.value * rhs.value, unit))
+}
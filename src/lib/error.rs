@@ -1,4 +1,8 @@
+use std::ops::Range;
+
 use crate::parser::Rule;
+use crate::unit::Unit;
+use pest::error::InputLocation;
 use rug::Integer;
 
 #[derive(thiserror::Error, Debug, Clone)]
@@ -19,6 +23,96 @@ pub enum Error {
     #[error("can't raise base to '{0}' power, max 2^32-1")]
     ExponentOverflow(Integer),
 
+    #[error("function '{0}' not found")]
+    FunctionNotFound(String),
+
+    #[error("function '{name}' takes {expected} argument(s), {found} given")]
+    ArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("division by zero")]
+    DivisionByZero,
+
+    #[error("'{0}' has no inverse modulo '{1}'")]
+    NoInverse(Integer, Integer),
+
+    #[error("incompatible units '{0}' and '{1}'")]
+    DimensionMismatch(Unit, Unit),
+
+    #[error("no unit both '{0}' and '{1}' can be converted to exactly")]
+    NoCommonUnit(Unit, Unit),
+
+    #[error("unknown unit '{0}'")]
+    UnknownUnit(String),
+
+    #[error("expected a number, found a list")]
+    ExpectedNumber,
+
+    #[error("invalid argument '{1}' for function '{0}'")]
+    InvalidArgument(String, Integer),
+
+    #[error("expected a list, found a number")]
+    ExpectedList,
+
+    #[error("index '{0}' out of bounds for a list of length {1}")]
+    IndexOutOfBounds(Integer, usize),
+
+    #[error("lists of different lengths ({0} and {1})")]
+    LengthMismatch(usize, usize),
+
+    #[error("function '{0}' needs a non-empty list")]
+    EmptyList(String),
+
+    #[error("function '{0}' needs a variable name as its first argument")]
+    ExpectedVariable(String),
+
+    #[error("exceeded the limit of {0} iterations")]
+    IterationLimit(usize),
+
     #[error("internal failure evaluating AST, please report this")]
     InternalAstFailure,
 }
+
+impl Error {
+    /// Returns the name of the error variant (e.g. `"VarNotFound"`), useful
+    /// for machine readable output where the message could change.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Error::VarNotFound(_) => "VarNotFound",
+            Error::NotValidVar(_) => "NotValidVar",
+            Error::ParseError { .. } => "ParseError",
+            Error::ExponentOverflow(_) => "ExponentOverflow",
+            Error::FunctionNotFound(_) => "FunctionNotFound",
+            Error::ArgumentCount { .. } => "ArgumentCount",
+            Error::DivisionByZero => "DivisionByZero",
+            Error::NoInverse(..) => "NoInverse",
+            Error::DimensionMismatch(..) => "DimensionMismatch",
+            Error::NoCommonUnit(..) => "NoCommonUnit",
+            Error::UnknownUnit(_) => "UnknownUnit",
+            Error::ExpectedNumber => "ExpectedNumber",
+            Error::InvalidArgument(..) => "InvalidArgument",
+            Error::ExpectedList => "ExpectedList",
+            Error::IndexOutOfBounds(..) => "IndexOutOfBounds",
+            Error::LengthMismatch(..) => "LengthMismatch",
+            Error::EmptyList(_) => "EmptyList",
+            Error::ExpectedVariable(_) => "ExpectedVariable",
+            Error::IterationLimit(_) => "IterationLimit",
+            Error::InternalAstFailure => "InternalAstFailure",
+        }
+    }
+
+    /// Returns the byte range of the evaluated expression the error refers to,
+    /// if it's known (currently only for [`Error::ParseError`]).
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            Error::ParseError { parse_failure, .. } => Some(match parse_failure.location {
+                InputLocation::Pos(x) => x..x,
+                InputLocation::Span((start, end)) => start..end,
+            }),
+            _ => None,
+        }
+    }
+}
@@ -2,6 +2,12 @@
 mod ast;
 pub mod context;
 pub mod error;
+mod number;
 mod parser;
+#[cfg(feature = "serde")]
+mod serde_int;
+pub mod token;
+pub mod unit;
+pub mod value;
 
 pub use rug::Integer;
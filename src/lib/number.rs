@@ -0,0 +1,99 @@
+//! Number theory helpers for the builtin functions which aren't provided by
+//! [`rug`] directly.
+use std::collections::BTreeMap;
+
+use crate::Integer;
+use rug::{integer::IsPrime, ops::Pow};
+
+/// Default number of Miller-Rabin rounds used to test primality, a composite
+/// passes with a probability of at most 4^-30.
+pub(crate) const PRIME_ROUNDS: u32 = 30;
+
+pub(crate) fn is_prime(n: &Integer, rounds: u32) -> bool {
+    n.is_probably_prime(rounds) != IsPrime::No
+}
+
+/// Factors `n` (which must be positive) into primes, returning each prime
+/// with its exponent in ascending order. The iterations of Pollard's rho
+/// (used for the large factors) are taken from `budget`, `None` is returned
+/// if it runs out.
+pub(crate) fn factor(n: &Integer, budget: &mut usize) -> Option<Vec<(Integer, u32)>> {
+    let mut factors = BTreeMap::new();
+    let mut n = n.clone();
+    // trial division finds the small factors much quicker than rho
+    let mut d = Integer::from(2);
+    while d < 1000 && n > 1 {
+        while n.is_divisible(&d) {
+            n /= &d;
+            *factors.entry(d.clone()).or_default() += 1;
+        }
+        d += if d == 2 { 1 } else { 2 };
+    }
+    let mut stack = vec![n];
+    while let Some(n) = stack.pop() {
+        if n == 1 {
+            continue;
+        } else if is_prime(&n, PRIME_ROUNDS) {
+            *factors.entry(n).or_default() += 1;
+        } else {
+            let d = rho(&n, budget)?;
+            stack.push(Integer::from(&n / &d));
+            stack.push(d);
+        }
+    }
+    Some(factors.into_iter().collect())
+}
+
+/// Finds a non-trivial factor of the composite `n` with Pollard's rho
+/// algorithm, each iteration is taken from `budget` and `None` is returned if
+/// it runs out.
+fn rho(n: &Integer, budget: &mut usize) -> Option<Integer> {
+    for c in 1u32.. {
+        let f = |x: &Integer| (Integer::from(x.square_ref()) + c) % n;
+        let mut x = Integer::from(2);
+        let mut y = x.clone();
+        let mut d = Integer::from(1);
+        while d == 1 {
+            *budget = budget.checked_sub(1)?;
+            x = f(&x);
+            y = f(&f(&y));
+            d = Integer::from(&x - &y).abs().gcd(n);
+        }
+        // the sequence cycled without finding a factor, so try another
+        // polynomial
+        if d != *n {
+            return Some(d);
+        }
+    }
+    unreachable!("every composite has a factor")
+}
+
+/// Euler's totient of `n` (which must be positive), see [`factor()`] for
+/// `budget`.
+pub(crate) fn totient(n: &Integer, budget: &mut usize) -> Option<Integer> {
+    let factors = factor(n, budget)?;
+    Some(factors.into_iter().fold(Integer::from(1), |acc, (p, e)| {
+        let power = Integer::from(&p).pow(e - 1);
+        acc * power * (p - 1)
+    }))
+}
+
+/// The positive divisors of `n` (which must be positive) in ascending order,
+/// see [`factor()`] for `budget`.
+pub(crate) fn divisors(n: &Integer, budget: &mut usize) -> Option<Vec<Integer>> {
+    let mut divisors = vec![Integer::from(1)];
+    for (p, e) in factor(n, budget)? {
+        let mut next = Vec::with_capacity(divisors.len() * (e as usize + 1));
+        for d in &divisors {
+            let mut power = d.clone();
+            next.push(power.clone());
+            for _ in 0..e {
+                power *= &p;
+                next.push(power.clone());
+            }
+        }
+        divisors = next;
+    }
+    divisors.sort_unstable();
+    Some(divisors)
+}
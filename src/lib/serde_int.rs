@@ -0,0 +1,176 @@
+//! Serde helpers encoding [`Integer`]s losslessly as decimal strings, used
+//! through `#[serde(with = "...")]` on the public types. Values with units are
+//! encoded as the decimal string followed by a space and the unit (e.g.
+//! `"3250 m"`).
+use std::str::FromStr;
+
+use crate::Integer;
+
+fn integer(s: &str) -> Result<Integer, String> {
+    Integer::from_str(s).map_err(|_| format!("'{s}' is not a decimal integer"))
+}
+
+/// Encodes an optional [`Integer`], e.g. the value of a result which may be a
+/// list.
+pub(crate) mod option {
+    use crate::Integer;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(
+        value: &Option<Integer>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(x) => serializer.collect_str(x),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Integer>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|x| super::integer(&x))
+            .transpose()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Encodes a [`Unit`](crate::unit::Unit) as a string, e.g. `"MiB/s"`.
+pub(crate) mod unit {
+    use crate::unit::Unit;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(unit: &Unit, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(unit)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Unit, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Encodes a [`Value`](crate::value::Value), lists are encoded as arrays.
+pub(crate) mod value {
+    use crate::unit::Quantity;
+    use crate::value::Value;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    pub(super) enum Repr {
+        Number(String),
+        List(Vec<Repr>),
+    }
+
+    pub(super) fn repr(value: &Value) -> Repr {
+        match value {
+            Value::Number(x) => Repr::Number(x.to_string()),
+            Value::List(values) => Repr::List(values.iter().map(repr).collect()),
+        }
+    }
+
+    pub(super) fn value(repr: Repr) -> Result<Value, String> {
+        match repr {
+            Repr::Number(s) => {
+                let (value, unit) = s.split_once(' ').unwrap_or((&s, "1"));
+                let value = super::integer(value)?;
+                let unit = unit.parse().map_err(|e| format!("{e}"))?;
+                Ok(Quantity::new(value, unit).into())
+            }
+            Repr::List(values) => values
+                .into_iter()
+                .map(value)
+                .collect::<Result<_, _>>()
+                .map(Value::List),
+        }
+    }
+
+    pub(crate) fn serialize<S: Serializer>(
+        value: &Value,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        repr(value).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Value, D::Error> {
+        value(Repr::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+/// Same as [`value`], for the items of a list result (if any).
+pub(crate) mod list {
+    use super::value::{repr, value, Repr};
+    use crate::value::Value;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(
+        list: &Option<Vec<Value>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        list.as_ref()
+            .map(|x| x.iter().map(repr).collect::<Vec<_>>())
+            .serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<Value>>, D::Error> {
+        Option::<Vec<Repr>>::deserialize(deserializer)?
+            .map(|x| x.into_iter().map(value).collect())
+            .transpose()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Same as [`value`], for a variable table; entries are serialized in
+/// sorted order so the output is stable, and names are validated with
+/// [`MathContext::var_valid()`] on deserialization.
+pub(crate) mod map {
+    use std::collections::HashMap;
+
+    use crate::context::MathContext;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    struct ValueRef<'a>(&'a crate::value::Value);
+
+    impl Serialize for ValueRef<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            super::value::serialize(self.0, serializer)
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(transparent)]
+    struct Value(#[serde(deserialize_with = "super::value::deserialize")] crate::value::Value);
+
+    pub(crate) fn serialize<S: Serializer>(
+        map: &HashMap<String, crate::value::Value>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut entries = map.iter().collect::<Vec<_>>();
+        entries.sort_unstable_by_key(|(k, _)| *k);
+        serializer.collect_map(entries.into_iter().map(|(k, v)| (k, ValueRef(v))))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<String, crate::value::Value>, D::Error> {
+        HashMap::<String, Value>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(k, Value(v))| {
+                if MathContext::var_valid(&k) && k != "_" {
+                    Ok((k, v))
+                } else {
+                    Err(de::Error::custom(format!("'{k}' not valid variable name")))
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,98 @@
+use std::ops::Range;
+
+use crate::parser::{MathParser, Rule};
+use pest::Parser;
+
+/// The kind of a [`Token`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TokenKind {
+    /// An integer literal, e.g. `42`
+    Literal,
+    /// A variable name, e.g. `x`
+    Variable,
+    /// The name of a function being called, e.g. `powmod` in `powmod(...)`
+    Function,
+    /// A unit after a literal or `to`, e.g. `km` in `3 km`
+    Unit,
+    /// A reserved word, e.g. `let`
+    Keyword,
+    /// An arithmetic operator, e.g. `+`
+    Operator,
+    /// An opening or closing parenthesis or bracket
+    Paren,
+    /// An argument separator or assignment (`,` or `=`)
+    Punctuation,
+    /// Anything that isn't valid in an expression
+    Unknown,
+}
+
+/// A token of a math expression, as returned by [`tokenize()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// The byte range of the token in the tokenized string
+    pub span: Range<usize>,
+}
+
+/// Splits a (possibly incomplete or invalid) math expression into tokens
+/// using the same grammar as [`crate::context::MathContext::eval()`],
+/// whitespace isn't included; useful for things like syntax highlighting.
+///
+/// # Examples
+/// ```
+/// use jnk::token::{tokenize, TokenKind};
+///
+/// let kinds = tokenize("x = powmod(2, y")
+///     .into_iter()
+///     .map(|x| x.kind)
+///     .collect::<Vec<_>>();
+///
+/// assert_eq!(
+///     kinds,
+///     [
+///         TokenKind::Variable,
+///         TokenKind::Punctuation,
+///         TokenKind::Function,
+///         TokenKind::Paren,
+///         TokenKind::Literal,
+///         TokenKind::Punctuation,
+///         TokenKind::Variable,
+///     ]
+/// );
+/// ```
+pub fn tokenize(expr: &str) -> Vec<Token> {
+    let pairs = match MathParser::parse(Rule::Tokens, expr) {
+        Ok(mut x) => x.next().unwrap().into_inner(),
+        Err(_) => unreachable!("Rule::Tokens matches any input"),
+    };
+
+    let mut tokens = pairs
+        .filter_map(|pair| {
+            let kind = match pair.as_rule() {
+                Rule::Keyword => TokenKind::Keyword,
+                Rule::Literal => TokenKind::Literal,
+                Rule::Variable => TokenKind::Variable,
+                Rule::Unit => TokenKind::Unit,
+                Rule::Operator => TokenKind::Operator,
+                Rule::Paren => TokenKind::Paren,
+                Rule::Punctuation => TokenKind::Punctuation,
+                Rule::Unknown => TokenKind::Unknown,
+                _ => return None,
+            };
+            let span = pair.as_span();
+            Some(Token {
+                kind,
+                span: span.start()..span.end(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // variables immediately followed by a parenthesis are function calls
+    for i in 1..tokens.len() {
+        if tokens[i - 1].kind == TokenKind::Variable && &expr[tokens[i].span.clone()] == "(" {
+            tokens[i - 1].kind = TokenKind::Function;
+        }
+    }
+    tokens
+}
@@ -0,0 +1,453 @@
+//! Units of measure, attached to values by writing them after a literal (e.g.
+//! `3 km` or `10 MiB/s`) and converted with `to` (e.g. `x to ft`).
+//!
+//! Supported units are the SI base units (`m`, `g`, `s`, `A`, `K`, `mol`,
+//! `cd`), `Hz`, `N`, `J` and `W`, all of which take SI prefixes (`km`, `ms`,
+//! `MHz`, ...), data sizes in bytes (`B`) and bits (`bit`) which take SI and
+//! binary prefixes (`kB`, `MiB`, `Gibit`, ...), units of time (`min`, `h`,
+//! `d`, `wk`) and imperial lengths (`inch`, `ft`, `yd`, `mi`).
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::Error;
+use crate::Integer;
+use rug::{ops::Pow, Rational};
+
+/// The exponent of each base dimension of a unit: length, mass, time,
+/// electric current, temperature, amount of substance, luminous intensity and
+/// information.
+type Dimension = [i32; 8];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Prefixes {
+    None,
+    Si,
+    SiAndBinary,
+}
+
+/// A named unit, `scale` is its size relative to the base units of its
+/// dimension as a fraction.
+struct Named {
+    name: &'static str,
+    scale: (u64, u64),
+    dimension: Dimension,
+    prefixes: Prefixes,
+}
+
+const fn named(
+    name: &'static str,
+    scale: (u64, u64),
+    dimension: Dimension,
+    prefixes: Prefixes,
+) -> Named {
+    Named {
+        name,
+        scale,
+        dimension,
+        prefixes,
+    }
+}
+
+const LENGTH: Dimension = [1, 0, 0, 0, 0, 0, 0, 0];
+const MASS: Dimension = [0, 1, 0, 0, 0, 0, 0, 0];
+const TIME: Dimension = [0, 0, 1, 0, 0, 0, 0, 0];
+const INFORMATION: Dimension = [0, 0, 0, 0, 0, 0, 0, 1];
+
+/// Every named unit, names are matched exactly before trying to split off a
+/// prefix (so `cd` is a candela rather than a centiday).
+const UNITS: &[Named] = &[
+    named("m", (1, 1), LENGTH, Prefixes::Si),
+    named("g", (1, 1), MASS, Prefixes::Si),
+    named("s", (1, 1), TIME, Prefixes::Si),
+    named("A", (1, 1), [0, 0, 0, 1, 0, 0, 0, 0], Prefixes::Si),
+    named("K", (1, 1), [0, 0, 0, 0, 1, 0, 0, 0], Prefixes::Si),
+    named("mol", (1, 1), [0, 0, 0, 0, 0, 1, 0, 0], Prefixes::Si),
+    named("cd", (1, 1), [0, 0, 0, 0, 0, 0, 1, 0], Prefixes::Si),
+    named("Hz", (1, 1), [0, 0, -1, 0, 0, 0, 0, 0], Prefixes::Si),
+    // the base unit of mass is the gram, so these are 1000 times their SI
+    // definitions
+    named("N", (1000, 1), [1, 1, -2, 0, 0, 0, 0, 0], Prefixes::Si),
+    named("J", (1000, 1), [2, 1, -2, 0, 0, 0, 0, 0], Prefixes::Si),
+    named("W", (1000, 1), [2, 1, -3, 0, 0, 0, 0, 0], Prefixes::Si),
+    named("B", (1, 1), INFORMATION, Prefixes::SiAndBinary),
+    named("bit", (1, 8), INFORMATION, Prefixes::SiAndBinary),
+    named("min", (60, 1), TIME, Prefixes::None),
+    named("h", (3600, 1), TIME, Prefixes::None),
+    named("d", (86400, 1), TIME, Prefixes::None),
+    named("wk", (604800, 1), TIME, Prefixes::None),
+    named("inch", (254, 10000), LENGTH, Prefixes::None),
+    named("ft", (3048, 10000), LENGTH, Prefixes::None),
+    named("yd", (9144, 10000), LENGTH, Prefixes::None),
+    named("mi", (1609344, 1000), LENGTH, Prefixes::None),
+];
+
+/// SI prefixes and their powers of ten.
+const SI_PREFIXES: &[(&str, i32)] = &[
+    ("E", 18),
+    ("P", 15),
+    ("T", 12),
+    ("G", 9),
+    ("M", 6),
+    ("k", 3),
+    ("h", 2),
+    ("da", 1),
+    ("d", -1),
+    ("c", -2),
+    ("m", -3),
+    ("u", -6),
+    ("µ", -6),
+    ("n", -9),
+    ("p", -12),
+    ("f", -15),
+    ("a", -18),
+];
+
+/// Binary prefixes and their powers of two.
+const BINARY_PREFIXES: &[(&str, u32)] = &[
+    ("Ki", 10),
+    ("Mi", 20),
+    ("Gi", 30),
+    ("Ti", 40),
+    ("Pi", 50),
+    ("Ei", 60),
+];
+
+/// Finds the scale and dimension of a single (possibly prefixed) unit name.
+fn lookup(name: &str) -> Option<(Rational, Dimension)> {
+    let scale = |x: &Named| Rational::from((x.scale.0, x.scale.1));
+    if let Some(x) = UNITS.iter().find(|x| x.name == name) {
+        return Some((scale(x), x.dimension));
+    }
+    let unit = |rest: &str, binary: bool| {
+        UNITS.iter().find(|x| {
+            x.name == rest
+                && match x.prefixes {
+                    Prefixes::None => false,
+                    Prefixes::Si => !binary,
+                    Prefixes::SiAndBinary => true,
+                }
+        })
+    };
+    for (prefix, exp) in BINARY_PREFIXES {
+        if let Some(x) = name.strip_prefix(prefix).and_then(|x| unit(x, true)) {
+            return Some((scale(x) * (Integer::from(1) << *exp), x.dimension));
+        }
+    }
+    for (prefix, exp) in SI_PREFIXES {
+        if let Some(x) = name.strip_prefix(prefix).and_then(|x| unit(x, false)) {
+            let factor = Rational::from(Integer::from(10).pow(exp.unsigned_abs()));
+            let factor = if *exp < 0 { factor.recip() } else { factor };
+            return Some((scale(x) * factor, x.dimension));
+        }
+    }
+    None
+}
+
+/// Every unit name, with and without the prefixes it takes.
+fn names() -> impl Iterator<Item = String> {
+    UNITS.iter().flat_map(|x| {
+        let si = SI_PREFIXES
+            .iter()
+            .filter(move |_| x.prefixes != Prefixes::None)
+            .map(|(prefix, _)| *prefix);
+        let binary = BINARY_PREFIXES
+            .iter()
+            .filter(move |_| x.prefixes == Prefixes::SiAndBinary)
+            .map(|(prefix, _)| *prefix);
+        std::iter::once("")
+            .chain(si)
+            .chain(binary)
+            .map(move |prefix| format!("{prefix}{}", x.name))
+    })
+}
+
+/// A (possibly compound) unit, e.g. `km` or `MiB/s`, made of named units
+/// raised to integer powers. The empty unit is written as `1`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Unit {
+    factors: BTreeMap<String, i32>,
+}
+
+impl Unit {
+    /// Creates the empty unit (`1`), the unit of plain numbers.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true if this is the empty unit.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.factors.is_empty()
+    }
+
+    fn dimension(&self) -> Dimension {
+        let mut dimension = Dimension::default();
+        for (name, exp) in &self.factors {
+            let (_, x) = lookup(name).expect("units are checked when created");
+            dimension.iter_mut().zip(x).for_each(|(a, b)| *a += b * exp);
+        }
+        dimension
+    }
+
+    fn scale(&self) -> Rational {
+        let mut scale = Rational::from(1);
+        for (name, exp) in &self.factors {
+            let (x, _) = lookup(name).expect("units are checked when created");
+            scale *= x.pow(*exp);
+        }
+        scale
+    }
+
+    /// Returns true if values in either unit can be converted to the other.
+    pub fn compatible(&self, other: &Self) -> bool {
+        self.dimension() == other.dimension()
+    }
+
+    /// Finds a unit both units are a whole multiple of, so values in either
+    /// can be converted to it exactly: the smaller of the two if it divides
+    /// the other, otherwise the largest named unit that divides both (e.g.
+    /// `B` for `KiB` and `kB`). Returns `None` if there isn't one.
+    fn common(&self, other: &Self) -> Option<Self> {
+        let (lhs, rhs) = (self.scale(), other.scale());
+        let whole = |x: Rational| *x.denom() == 1;
+        if whole(Rational::from(&lhs / &rhs)) {
+            return Some(other.clone());
+        } else if whole(Rational::from(&rhs / &lhs)) {
+            return Some(self.clone());
+        }
+        let dimension = self.dimension();
+        names()
+            .filter_map(|name| {
+                let (scale, x) = lookup(&name)?;
+                let divides =
+                    whole(Rational::from(&lhs / &scale)) && whole(Rational::from(&rhs / &scale));
+                (x == dimension && divides).then_some((scale, name))
+            })
+            .max_by(|a, b| a.0.cmp(&b.0))
+            .map(|(_, name)| Self {
+                factors: BTreeMap::from([(name, 1)]),
+            })
+    }
+
+    fn combine(&self, other: &Self, sign: i32) -> Self {
+        let mut factors = self.factors.clone();
+        for (name, exp) in &other.factors {
+            *factors.entry(name.to_owned()).or_default() += exp * sign;
+        }
+        factors.retain(|_, x| *x != 0);
+        Self { factors }
+    }
+
+    pub(crate) fn mul(&self, other: &Self) -> Self {
+        self.combine(other, 1)
+    }
+
+    pub(crate) fn div(&self, other: &Self) -> Self {
+        self.combine(other, -1)
+    }
+
+    pub(crate) fn pow(&self, exp: i32) -> Self {
+        let mut factors = self.factors.clone();
+        factors.values_mut().for_each(|x| *x *= exp);
+        factors.retain(|_, x| *x != 0);
+        Self { factors }
+    }
+
+    /// Converts `value` from this unit to `to`, rounding towards zero.
+    /// Returns [`Error::DimensionMismatch`] if the units aren't compatible.
+    ///
+    /// # Examples
+    /// ```
+    /// use jnk::unit::Unit;
+    ///
+    /// let km: Unit = "km".parse().unwrap();
+    /// let ft: Unit = "ft".parse().unwrap();
+    /// assert_eq!(km.convert(2.into(), &ft).unwrap(), 6561);
+    /// assert!(km.convert(2.into(), &"s".parse().unwrap()).is_err());
+    /// ```
+    pub fn convert(&self, value: Integer, to: &Self) -> Result<Integer, Error> {
+        if self == to {
+            Ok(value)
+        } else if self.compatible(to) {
+            let value = Rational::from(value) * self.scale() / to.scale();
+            Ok(value.trunc().into_numer_denom().0)
+        } else {
+            Err(Error::DimensionMismatch(self.clone(), to.clone()))
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    /// Writes the unit in the syntax it's parsed from, e.g. `kg*m/s^2`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let write = |f: &mut fmt::Formatter<'_>, name: &str, exp: i32| match exp {
+            1 => write!(f, "{name}"),
+            _ => write!(f, "{name}^{exp}"),
+        };
+        let mut numerator = self.factors.iter().filter(|(_, x)| **x > 0).peekable();
+        if numerator.peek().is_none() {
+            // there's nothing to divide, so use negative exponents
+            let mut factors = self.factors.iter();
+            match factors.next() {
+                Some((name, exp)) => write(f, name, *exp)?,
+                None => return write!(f, "1"),
+            }
+            for (name, exp) in factors {
+                f.write_str("*")?;
+                write(f, name, *exp)?;
+            }
+            return Ok(());
+        }
+        for (i, (name, exp)) in numerator.enumerate() {
+            if i > 0 {
+                f.write_str("*")?;
+            }
+            write(f, name, *exp)?;
+        }
+        for (name, exp) in self.factors.iter().filter(|(_, x)| **x < 0) {
+            f.write_str("/")?;
+            write(f, name, -exp)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Unit {
+    type Err = Error;
+
+    /// Parses a unit, named units can be multiplied (`*`), divided (`/`) and
+    /// raised to integer powers (`^`) without any whitespace, e.g. `kg*m/s^2`.
+    /// `1` is the empty unit.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut unit = Self::new();
+        if s == "1" {
+            return Ok(unit);
+        }
+        let invalid = || Error::UnknownUnit(s.to_owned());
+        let mut rest = s;
+        let mut sign = 1;
+        loop {
+            let end = rest
+                .find(|x: char| !x.is_alphabetic())
+                .unwrap_or(rest.len());
+            let name = &rest[..end];
+            if name.is_empty() {
+                return Err(invalid());
+            }
+            if lookup(name).is_none() {
+                return Err(Error::UnknownUnit(name.to_owned()));
+            }
+            rest = &rest[end..];
+            let mut exp = 1;
+            if let Some(x) = rest.strip_prefix('^') {
+                let end = x
+                    .char_indices()
+                    .find(|&(i, c)| !(c.is_ascii_digit() || (i == 0 && c == '-')))
+                    .map_or(x.len(), |(i, _)| i);
+                exp = x[..end].parse().map_err(|_| invalid())?;
+                rest = &x[end..];
+            }
+            let factor = Self {
+                factors: BTreeMap::from([(name.to_owned(), exp * sign)]),
+            };
+            unit = unit.mul(&factor);
+            match rest.chars().next() {
+                None => break Ok(unit),
+                Some('*') => sign = 1,
+                Some('/') => sign = -1,
+                Some(_) => break Err(invalid()),
+            }
+            rest = &rest[1..];
+        }
+    }
+}
+
+/// A value with a unit, variables and results of expressions are quantities
+/// (plain numbers have the empty unit).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Quantity {
+    pub value: Integer,
+    pub unit: Unit,
+}
+
+impl Quantity {
+    #[inline]
+    pub fn new(value: Integer, unit: Unit) -> Self {
+        Self { value, unit }
+    }
+
+    /// Converts both quantities exactly to a common unit (the smaller of the
+    /// two if the other is a whole multiple of it, e.g. `m` for `km` and `m`,
+    /// otherwise the largest unit both are, e.g. `B` for `KiB` and `kB`),
+    /// returning their values and that unit. Returns
+    /// [`Error::DimensionMismatch`] if the units aren't compatible, or
+    /// [`Error::NoCommonUnit`] if there's no such unit.
+    pub(crate) fn align(self, rhs: Self) -> Result<(Integer, Integer, Unit), Error> {
+        if self.unit == rhs.unit {
+            return Ok((self.value, rhs.value, self.unit));
+        } else if !self.unit.compatible(&rhs.unit) {
+            return Err(Error::DimensionMismatch(self.unit, rhs.unit));
+        }
+        let unit = self
+            .unit
+            .common(&rhs.unit)
+            .ok_or_else(|| Error::NoCommonUnit(self.unit.clone(), rhs.unit.clone()))?;
+        let lhs = self.unit.convert(self.value, &unit)?;
+        let rhs = rhs.unit.convert(rhs.value, &unit)?;
+        Ok((lhs, rhs, unit))
+    }
+
+    /// Compares two quantities exactly, rather than after converting one to
+    /// the other's unit (so `1 m` is greater than `3 ft`). Returns
+    /// [`Error::DimensionMismatch`] if the units aren't compatible.
+    ///
+    /// # Examples
+    /// ```
+    /// use jnk::unit::Quantity;
+    /// use std::cmp::Ordering;
+    ///
+    /// let m = Quantity::new(1.into(), "m".parse().unwrap());
+    /// let ft = Quantity::new(3.into(), "ft".parse().unwrap());
+    /// assert_eq!(m.compare(&ft).unwrap(), Ordering::Greater);
+    /// ```
+    pub fn compare(&self, rhs: &Self) -> Result<Ordering, Error> {
+        if self.unit == rhs.unit {
+            return Ok(self.value.cmp(&rhs.value));
+        } else if !self.unit.compatible(&rhs.unit) {
+            return Err(Error::DimensionMismatch(
+                self.unit.clone(),
+                rhs.unit.clone(),
+            ));
+        }
+        let lhs = Rational::from(&self.value) * self.unit.scale();
+        Ok(lhs.cmp(&(Rational::from(&rhs.value) * rhs.unit.scale())))
+    }
+
+    /// Returns the value as a plain number, converting it if its unit cancels
+    /// out (e.g. `km/m`), otherwise returns [`Error::DimensionMismatch`].
+    pub fn plain(self) -> Result<Integer, Error> {
+        self.unit.convert(self.value, &Unit::new())
+    }
+}
+
+impl From<Integer> for Quantity {
+    #[inline]
+    fn from(value: Integer) -> Self {
+        Self::new(value, Unit::new())
+    }
+}
+
+impl fmt::Display for Quantity {
+    /// Writes the value followed by its unit (if it isn't empty), e.g.
+    /// `3250 m`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value)?;
+        if !self.unit.is_empty() {
+            write!(f, " {}", self.unit)?;
+        }
+        Ok(())
+    }
+}
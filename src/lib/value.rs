@@ -0,0 +1,179 @@
+use std::fmt;
+
+use crate::error::Error;
+use crate::unit::{Quantity, Unit};
+use crate::Integer;
+
+/// A value an expression can evaluate to, either a number (with a unit) or a
+/// list of values, e.g. the result of `factor(360)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Number(Quantity),
+    List(Vec<Value>),
+}
+
+impl Value {
+    /// Returns the number, if this is one.
+    pub fn as_number(&self) -> Option<&Quantity> {
+        match self {
+            Value::Number(x) => Some(x),
+            Value::List(_) => None,
+        }
+    }
+
+    /// Returns the number, or [`Error::ExpectedNumber`] if this is a list.
+    pub fn into_number(self) -> Result<Quantity, Error> {
+        match self {
+            Value::Number(x) => Ok(x),
+            Value::List(_) => Err(Error::ExpectedNumber),
+        }
+    }
+
+    /// Returns the items of the list, or [`Error::ExpectedList`] if this is a
+    /// number.
+    pub fn into_list(self) -> Result<Vec<Value>, Error> {
+        match self {
+            Value::Number(_) => Err(Error::ExpectedList),
+            Value::List(values) => Ok(values),
+        }
+    }
+
+    /// Returns the unit of the number, or `None` if this is a list.
+    pub fn unit(&self) -> Option<&Unit> {
+        self.as_number().map(|x| &x.unit)
+    }
+
+    /// Returns whether two values are equal (as `==` does for two numbers or
+    /// two lists), comparing numbers exactly with [`Quantity::compare()`] and
+    /// lists item by item. Lists of different lengths, or a list and a
+    /// number, are never equal.
+    ///
+    /// # Examples
+    /// ```
+    /// use jnk::context::MathContext;
+    ///
+    /// let mut ctx = MathContext::new();
+    /// let km = ctx.eval("[1 km, 2]").unwrap().result();
+    /// let m = ctx.eval("[1000 m, 2]").unwrap().result();
+    /// assert!(km.equals(&m).unwrap());
+    /// ```
+    pub fn equals(&self, rhs: &Value) -> Result<bool, Error> {
+        match (self, rhs) {
+            (Value::Number(lhs), Value::Number(rhs)) => Ok(lhs.compare(rhs)?.is_eq()),
+            (Value::List(lhs), Value::List(rhs)) if lhs.len() == rhs.len() => {
+                for (lhs, rhs) in lhs.iter().zip(rhs) {
+                    if !lhs.equals(rhs)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+impl Value {
+    /// Applies `f` to the number, or to every number in the list (including
+    /// nested ones).
+    pub(crate) fn map<F>(self, f: &mut F) -> Result<Value, Error>
+    where
+        F: FnMut(Quantity) -> Result<Quantity, Error>,
+    {
+        match self {
+            Value::Number(x) => f(x).map(Value::Number),
+            Value::List(values) => values
+                .into_iter()
+                .map(|x| x.map(f))
+                .collect::<Result<_, _>>()
+                .map(Value::List),
+        }
+    }
+
+    /// Applies the binary operation `f` to two values, a number paired with a
+    /// list is applied to every item of the list (e.g. `[1, 2] * 3` gives
+    /// `[3, 6]`), and two lists are paired item by item, in which case they
+    /// must be the same length.
+    pub(crate) fn broadcast<F>(self, rhs: Value, f: &mut F) -> Result<Value, Error>
+    where
+        F: FnMut(Quantity, Quantity) -> Result<Quantity, Error>,
+    {
+        match (self, rhs) {
+            (Value::Number(lhs), Value::Number(rhs)) => f(lhs, rhs).map(Value::Number),
+            (Value::Number(lhs), rhs @ Value::List(_)) => rhs.map(&mut |rhs| f(lhs.clone(), rhs)),
+            (lhs @ Value::List(_), Value::Number(rhs)) => lhs.map(&mut |lhs| f(lhs, rhs.clone())),
+            (Value::List(lhs), Value::List(rhs)) => {
+                if lhs.len() != rhs.len() {
+                    return Err(Error::LengthMismatch(lhs.len(), rhs.len()));
+                }
+                lhs.into_iter()
+                    .zip(rhs)
+                    .map(|(lhs, rhs)| lhs.broadcast(rhs, f))
+                    .collect::<Result<_, _>>()
+                    .map(Value::List)
+            }
+        }
+    }
+}
+
+impl Default for Value {
+    fn default() -> Self {
+        Value::Number(Quantity::default())
+    }
+}
+
+impl From<Quantity> for Value {
+    #[inline]
+    fn from(value: Quantity) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<Integer> for Value {
+    #[inline]
+    fn from(value: Integer) -> Self {
+        Value::Number(value.into())
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    #[inline]
+    fn from(values: Vec<Value>) -> Self {
+        Value::List(values)
+    }
+}
+
+impl fmt::Display for Value {
+    /// Writes numbers followed by their unit (if they have one) and lists as
+    /// `[1, 2, 3]`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(x) => write!(f, "{x}"),
+            Value::List(values) => {
+                f.write_str("[")?;
+                for (i, x) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{x}")?;
+                }
+                f.write_str("]")
+            }
+        }
+    }
+}
+
+/// Plain numbers (without a unit) are equal to integers.
+impl PartialEq<Integer> for Value {
+    fn eq(&self, other: &Integer) -> bool {
+        self.as_number()
+            .is_some_and(|x| x.unit.is_empty() && x.value == *other)
+    }
+}
+
+impl PartialEq<i32> for Value {
+    fn eq(&self, other: &i32) -> bool {
+        self.as_number()
+            .is_some_and(|x| x.unit.is_empty() && x.value == *other)
+    }
+}
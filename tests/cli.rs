@@ -0,0 +1,440 @@
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+/// Returns a temporary directory for this test run's files.
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("jnk-cli-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Writes `script` to a temporary file named `name`, returning its path.
+fn script(name: &str, script: &str) -> String {
+    let path = temp_dir().join(name);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).unwrap();
+    }
+    fs::write(&path, script).unwrap();
+    path.to_str().unwrap().to_owned()
+}
+
+/// Runs jnk with `args` (ignoring any user configuration), returning its exit
+/// status, standard output and standard error.
+fn jnk(args: &[&str]) -> (i32, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_jnk"))
+        .args(args)
+        .env("XDG_CONFIG_HOME", temp_dir())
+        .env("NO_COLOR", "1")
+        .env("RUST_BACKTRACE", "0")
+        .output()
+        .unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+/// Runs the jnk repl with `args`, typing `input` into it and with `init` as
+/// the init file (in a config directory named `name`), returning its exit
+/// status and standard output.
+fn repl(name: &str, init: &str, args: &[&str], input: &str) -> (i32, String) {
+    let config = temp_dir().join(name);
+    script(&format!("{name}/jnk/init.jnk"), init);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_jnk"))
+        .args(["--quiet", "--no-history"])
+        .args(args)
+        .env("XDG_CONFIG_HOME", config)
+        .env("NO_COLOR", "1")
+        .env("RUST_BACKTRACE", "0")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn check_defines() {
+    let path = script("defines.jnk", "y = x * 2\ny\n");
+
+    let (status, out, _) = jnk(&["check", &path]);
+    assert_eq!(status, 1);
+    assert_eq!(
+        out,
+        format!("{path}:1:5: error: 'x' used before assignment\n")
+    );
+    let (status, out, _) = jnk(&["check", "-D", "x=2", &path]);
+    assert_eq!((status, out.as_str()), (0, ""));
+}
+
+#[test]
+fn for_loops() {
+    let path = script(
+        "for.jnk",
+        "\
+total = 0
+for i in 1..4 {
+    total = total + i
+}
+total
+i
+for j in 1..=4 { total = total + j }
+total
+for k in 5..1 { total = 0 }
+total
+",
+    );
+    let (status, out, _) = jnk(&[&path]);
+    assert_eq!(status, 0);
+    // the loop variable keeps its value from the last iteration
+    assert_eq!(out, "6\n3\n16\n16\n");
+}
+
+#[test]
+fn if_else_chains() {
+    let path = script(
+        "if.jnk",
+        "\
+for x in 0..4 {
+    if x == 0 {
+        print \"zero\"
+    } else if x == 1 {
+        print \"one\"
+    } else if x == 2 {
+        print \"two\"
+    } else {
+        print \"many\"
+    }
+}
+",
+    );
+    let (status, out, _) = jnk(&[&path]);
+    assert_eq!((status, out.as_str()), (0, "zero\none\ntwo\nmany\n"));
+}
+
+#[test]
+fn max_iterations() {
+    let path = script("while.jnk", "x = 0\nwhile 1 { x = x + 1 }\n");
+    let (status, _, err) = jnk(&["--max-iterations", "5", &path]);
+    assert_eq!(status, 1);
+    assert!(err.contains(&format!(
+        "{path}:2:7: loop exceeded 5 iterations (see --max-iterations)"
+    )));
+
+    let path = script("range.jnk", "for i in 0..5 { i; }\nfor i in 0..=5 { i; }\n");
+    let (status, _, err) = jnk(&["--max-iterations", "5", &path]);
+    assert_eq!(status, 1);
+    assert!(err.contains(&format!("{path}:2:10: loop exceeded 5 iterations")));
+}
+
+#[test]
+fn check_diagnostics() {
+    let path = script(
+        "check.jnk",
+        "\
+if c { y = 1 } else { z = 2 }
+y
+n = 0
+while n < 3 { n = n + 1 }
+w = 5
+for i in 0..n { v = v + i }
+",
+    );
+    let (status, out, _) = jnk(&["check", &path]);
+    assert_eq!(status, 1);
+    assert_eq!(
+        out,
+        format!(
+            "\
+{path}:1:4: error: 'c' used before assignment
+{path}:1:23: warning: value assigned to 'z' is never read
+{path}:5:1: warning: value assigned to 'w' is never read
+{path}:6:17: error: 'v' used before assignment
+"
+        )
+    );
+}
+
+#[test]
+fn test_subcommand() {
+    script(
+        "tests/a_test.jnk",
+        "include \"lib.jnk\"\nassert x > 1\nassert_eq x + 2, 4\nassert_eq max(1, 3), 3\n",
+    );
+    script(
+        "tests/sub/test_b.jnk",
+        "assert_eq 1 + 1, 3\ny = 1 / 0\nassert 0\nassert 1\n",
+    );
+    script("tests/c_test.jnk", "assert 1\nexit 3\nassert 1\n");
+    // only test scripts are run, not the scripts they include
+    script("tests/lib.jnk", "x = 2\nassert x == 2\n");
+    script("tests/notes_test.txt", "assert 0\n");
+
+    let dir = temp_dir().join("tests");
+    let dir = dir.to_str().unwrap();
+    let (status, out, _) = jnk(&["test", dir]);
+    assert_eq!(status, 1);
+    assert_eq!(
+        out,
+        format!(
+            "\
+PASS {dir}/lib.jnk:2:8: x == 2
+PASS {dir}/a_test.jnk:2:8: x > 1
+PASS {dir}/a_test.jnk:3:11: x + 2 == 4
+PASS {dir}/a_test.jnk:4:11: max(1, 3) == 3
+PASS {dir}/c_test.jnk:1:8: 1
+FAIL {dir}/c_test.jnk: exited with status 3
+FAIL {dir}/sub/test_b.jnk:1:11: assertion failed: 1 + 1 == 3 (2 != 3)
+FAIL {dir}/sub/test_b.jnk:2:1: division by zero
+FAIL {dir}/sub/test_b.jnk:3:8: assertion failed: 0
+PASS {dir}/sub/test_b.jnk:4:8: 1
+
+test result: 6 passed, 4 failed (3 file(s))
+"
+        )
+    );
+
+    // scripts named on the command line are run whatever they're called
+    let (status, out, _) = jnk(&["test", &format!("{dir}/lib.jnk")]);
+    assert_eq!(status, 0);
+    assert!(out.ends_with("test result: 1 passed, 0 failed (1 file(s))\n"));
+    let (status, out, _) = jnk(&["test", &format!("{dir}/a_test.jnk")]);
+    assert_eq!(status, 0);
+    assert!(out.ends_with("test result: 4 passed, 0 failed (1 file(s))\n"));
+}
+
+#[test]
+fn script_arguments() {
+    let path = script(
+        "args.jnk",
+        "if argc < 2 {\n    exit 2\n}\nx = arg1 * 2\nx + arg2\n",
+    );
+    let (status, out, _) = jnk(&[&path, "3", "0x10"]);
+    assert_eq!((status, out.as_str()), (0, "22\n"));
+    let (status, _, _) = jnk(&[&path, "3"]);
+    assert_eq!(status, 2);
+
+    // the arguments aren't known when checking, but are assumed to be set
+    let (status, out, _) = jnk(&["check", &path]);
+    assert_eq!((status, out.as_str()), (0, ""));
+    let path = script("args0.jnk", "arg0 + arg01 + args\n");
+    let (_, out, _) = jnk(&["check", &path]);
+    assert_eq!(out.lines().count(), 3);
+}
+
+#[test]
+fn exit_statuses() {
+    let missing = temp_dir().join("missing.jnk");
+    for (args, expected) in [
+        (vec!["-e", "1 / 0"], 1),
+        (vec!["--format", "yaml", "-e", "1"], 2),
+        (vec!["-D", "1x=2", "-e", "1"], 2),
+        (vec!["-e", "1 +"], 3),
+        (vec![missing.to_str().unwrap()], 4),
+    ] {
+        assert_eq!(jnk(&args).0, expected, "{args:?}");
+    }
+    // errors are reported without eyre's location or backtrace
+    assert_eq!(
+        jnk(&["-D", "1x=2", "-e", "1"]).2,
+        "ERROR invalid arguments: invalid --define: '1x' is not a valid variable name\n"
+    );
+    assert_eq!(
+        jnk(&["-e", "1 / 0"]).2,
+        "ERROR <expr>:1:1: division by zero\n"
+    );
+
+    for (text, expected, out) in [
+        ("print \"a\"\nexit 0 - 1\nprint \"b\"\n", 255, "a\n"),
+        ("exit 257\n", 1, ""),
+        ("if 1 { return 7 }\n", 7, ""),
+        ("1\nexit\n2\n", 0, "1\n"),
+    ] {
+        let path = script("exit.jnk", text);
+        assert_eq!(
+            jnk(&[&path]),
+            (expected, out.to_owned(), String::new()),
+            "{text}"
+        );
+    }
+}
+
+#[test]
+fn unit_assertions() {
+    let path = script(
+        "units.jnk",
+        "\
+assert_eq 1 km, 1000 m
+assert_eq 1 km, 1 m
+assert 1 m > 3 ft
+for i in 0..1 km { }
+assert_eq [1 km, 2], [1000 m, 2]
+assert_eq 1 m, 1 s
+",
+    );
+    let (status, out, _) = jnk(&["test", &path]);
+    assert_eq!(status, 1);
+    assert_eq!(
+        out,
+        format!(
+            "\
+PASS {path}:1:11: 1 km == 1000 m
+FAIL {path}:2:11: assertion failed: 1 km == 1 m (1 km != 1 m)
+PASS {path}:3:8: 1 m > 3 ft
+FAIL {path}:4:13: incompatible units 'km' and '1'
+PASS {path}:5:11: [1 km, 2] == [1000 m, 2]
+FAIL {path}:6:11: incompatible units 'm' and 's'
+
+test result: 3 passed, 3 failed (1 file(s))
+"
+        )
+    );
+}
+
+#[test]
+fn subcommand_flags() {
+    let path = script("flags.jnk", "x = c * 2\nassert_eq x, 2\n");
+    for args in [
+        vec!["-q", "check", "-D", "c=1", &path],
+        vec!["--no-init", "check", "-D", "c=1", &path],
+        vec!["-D", "c=1", "check", &path],
+        vec!["-D", "c=1", "check", &path, "--max-iterations", "5"],
+    ] {
+        assert_eq!(jnk(&args), (0, String::new(), String::new()), "{args:?}");
+    }
+    // diagnostics and test results are only printed as text
+    for command in ["check", "test"] {
+        let (status, out, err) = jnk(&["-f", "json", command, &path]);
+        assert_eq!((status, out.as_str()), (2, ""));
+        assert!(err.contains("--format can't be used with check or test"));
+    }
+    assert_eq!(jnk(&["-D", "c=1", "-f", "text", "check", &path]).0, 0);
+
+    // the iteration limit applies to the test runner too, before or after
+    let path = script("flags_test.jnk", "assert sum(k, 1, 10, k) > 0\n");
+    for args in [
+        vec!["--max-iterations", "5", "test", &path],
+        vec!["test", &path, "--max-iterations", "5"],
+        vec!["-k", "test", &path],
+    ] {
+        let (status, out, _) = jnk(&args);
+        let limited = args.contains(&"5");
+        assert_eq!(status, limited as i32, "{args:?}");
+        assert_eq!(out.contains("exceeded the limit of 5 iterations"), limited);
+    }
+}
+
+#[test]
+fn json_output() {
+    let (status, out, _) = jnk(&[
+        "-k", "-f", "json", "-e", "x = 2 km", "-e", "x / 0", "-e", "\"\\t\"",
+    ]);
+    assert_eq!(status, 1);
+    assert_eq!(
+        out,
+        r#"[
+  {"line":1,"input":"x = 2 km","var":"x","value":"2","unit":"km","error":null},
+  {"line":2,"input":"x / 0","var":null,"value":null,"unit":null,"error":{"kind":"DivisionByZero","message":"division by zero","span":null}},
+  {"line":3,"input":"\"\\t\"","var":null,"value":null,"unit":null,"error":{"kind":"ParseError","message":"parse error: expected Main","span":[0,0]}}
+]
+"#
+    );
+
+    let (_, out, _) = jnk(&["-f", "jsonl", "-e", "xs = [1, 2]"]);
+    assert_eq!(
+        out,
+        "{\"line\":1,\"input\":\"xs = [1, 2]\",\"var\":\"xs\",\"value\":\"[1, 2]\",\"unit\":null,\"error\":null}\n"
+    );
+}
+
+#[test]
+fn init_file() {
+    let init = "rate = 5\nbase = 100\n";
+    assert_eq!(
+        repl("init", init, &[], "rate + base\n"),
+        (0, "105\n".to_owned())
+    );
+    // the command line takes precedence over the init file
+    assert_eq!(
+        repl("init", init, &["-D", "rate=7"], "rate + base\n"),
+        (0, "107\n".to_owned())
+    );
+    assert_eq!(
+        repl("init", init, &["--no-init", "-D", "rate=7"], "rate\nbase\n"),
+        (0, "7\nERROR variable not found: 'base'\n".to_owned())
+    );
+    // and both are kept by :reset
+    assert_eq!(
+        repl(
+            "init",
+            init,
+            &["-D", "rate=7"],
+            "rate = 1\nx = 2\n:reset\nrate + base\nx\n"
+        ),
+        (0, "107\nERROR variable not found: 'x'\n".to_owned())
+    );
+}
+
+#[test]
+fn print_errors() {
+    let path = script(
+        "print.jnk",
+        "x = 255\nprint \"a {x:hex} b {y} c\"\nprint \"{x} done\"\n",
+    );
+    let (status, out, err) = jnk(&["--keep-going", &path]);
+    assert_eq!(status, 1);
+    // the failed print is skipped rather than printed up to the error
+    assert_eq!(out, "255 done\n");
+    assert!(err.contains(&format!("{path}:2:21: 'y' not in variable table")));
+}
+
+#[test]
+fn csv_output() {
+    let (status, out, _) = jnk(&[
+        "-k",
+        "-f",
+        "csv",
+        "-e",
+        "x = 2 km",
+        "-e",
+        "xs = [1, 2, 3]",
+        "-e",
+        "xs / 0",
+    ]);
+    assert_eq!(status, 1);
+    assert_eq!(
+        out,
+        "\
+line,input,var,value,unit,error,message,start,end
+1,x = 2 km,x,2,km,,,,
+2,\"xs = [1, 2, 3]\",xs,\"[1, 2, 3]\",,,,,
+3,xs / 0,,,,DivisionByZero,division by zero,,
+"
+    );
+}
+
+#[test]
+fn repl_del() {
+    let input = "xs = [1, 2]\n:del xs\n:del xs\nxs\n";
+    let (status, out) = repl("del", "", &[], input);
+    assert_eq!(status, 0);
+    assert_eq!(
+        out,
+        "ERROR variable not found: 'xs'\nERROR variable not found: 'xs'\n"
+    );
+}
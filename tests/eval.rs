@@ -7,7 +7,7 @@ fn basic_eval() {
 
     let result = ctx.eval("((3 + 1 - 2) * 4 / 2) ^ 2").unwrap();
     let sixteen = Integer::from(16);
-    assert_eq!(result.value, sixteen);
+    assert_eq!(result.value.unwrap(), sixteen);
 }
 
 #[test]
@@ -30,7 +30,7 @@ fn last_eval() {
     assert!(result.var.is_some());
 
     let result = ctx.eval("_ * 4").unwrap();
-    assert_eq!(result.value, Integer::from(12));
+    assert_eq!(result.value.unwrap(), Integer::from(12));
 }
 
 #[test]
@@ -40,3 +40,106 @@ fn underscore_disregard() {
     let result = ctx.eval("_ = 1 + 1").unwrap();
     assert!(result.var.is_none());
 }
+
+#[test]
+fn trivial_base_large_exponent() {
+    let ctx = MathContext::new();
+
+    assert_eq!(ctx.eval_disregard("1 ^ (2 ^ 40)").unwrap(), 1);
+    assert_eq!(ctx.eval_disregard("0 ^ (2 ^ 40)").unwrap(), 0);
+    assert_eq!(ctx.eval_disregard("(-1) ^ (2 ^ 40)").unwrap(), 1);
+    assert_eq!(ctx.eval_disregard("(-1) ^ (2 ^ 40 + 1)").unwrap(), -1);
+    assert!(ctx.eval_disregard("2 ^ (2 ^ 40)").is_err());
+}
+
+#[test]
+fn modular_power() {
+    let ctx = MathContext::new();
+
+    let result = ctx
+        .eval_disregard("powmod(3, 2 ^ 100, 1000000007)")
+        .unwrap();
+    let expected = Integer::from(3)
+        .pow_mod(&(Integer::from(1) << 100), &Integer::from(1000000007))
+        .unwrap();
+    assert_eq!(result, expected);
+
+    assert_eq!(ctx.eval_disregard("powmod(7, -5, 1000)").unwrap(), 943);
+    assert!(ctx.eval_disregard("powmod(2, -5, 1000)").is_err());
+    assert!(ctx.eval_disregard("powmod(2, 5, 0)").is_err());
+}
+
+#[test]
+fn let_expression() {
+    let mut ctx = MathContext::new();
+
+    let result = ctx.eval("let r = 5 in 3 * r ^ 2").unwrap();
+    assert_eq!(result.value.unwrap(), 75);
+    assert!(ctx.var_get("r").is_none());
+
+    let result = ctx
+        .eval("(let a = 2 in let b = a + 1 in a * b) + 1")
+        .unwrap();
+    assert_eq!(result.value.unwrap(), 7);
+
+    ctx.eval("x = 10").unwrap();
+    let result = ctx.eval("let x = 1 in x + (let x = 2 in x)").unwrap();
+    assert_eq!(result.value.unwrap(), 3);
+    assert_eq!(*ctx.var_get("x").unwrap(), 10);
+
+    assert!(ctx.eval("let = 1").is_err());
+    assert!(ctx.eval("letter = 1").is_ok());
+}
+
+#[test]
+fn scoped_context() {
+    let mut ctx = MathContext::new();
+    ctx.eval("x = 1").unwrap();
+
+    let result = ctx.with_scope(|scoped| {
+        scoped.eval("x = 2").unwrap();
+        scoped.eval("y = x + 1").unwrap();
+        scoped.eval("x * y").unwrap().value.unwrap()
+    });
+    assert_eq!(result, 6);
+
+    assert_eq!(*ctx.var_get("x").unwrap(), 1);
+    assert!(ctx.var_get("y").is_none());
+    assert_eq!(*ctx.last().unwrap(), 1);
+}
+
+#[test]
+fn analyze_expression() {
+    let analysis = MathContext::analyze("y = powmod(x, let e = 2 in e * n, m) + x").unwrap();
+    assert_eq!(analysis.var.as_deref(), Some("y"));
+    assert_eq!(analysis.reads, ["x", "n", "m", "x"]);
+
+    assert!(MathContext::analyze("y = (1 +").is_err());
+}
+
+#[test]
+fn division_by_zero() {
+    let ctx = MathContext::new();
+
+    let err = ctx.eval_disregard("1 / (2 - 2)").unwrap_err();
+    assert_eq!(err.name(), "DivisionByZero");
+    assert!(err.span().is_none());
+
+    let err = ctx.eval_disregard("1 +").unwrap_err();
+    assert_eq!(err.name(), "ParseError");
+    assert_eq!(err.span(), Some(3..3));
+}
+
+#[test]
+fn comparisons() {
+    let mut ctx = MathContext::new();
+    ctx.eval("x = 5").unwrap();
+
+    assert_eq!(ctx.eval("x == 5").unwrap().value.unwrap(), 1);
+    assert_eq!(ctx.eval("x != 5").unwrap().value.unwrap(), 0);
+    assert_eq!(ctx.eval("x < 2 ^ 3").unwrap().value.unwrap(), 1);
+    assert_eq!(ctx.eval("x + 1 <= 5").unwrap().value.unwrap(), 0);
+    assert_eq!(ctx.eval("x > 0 - 1").unwrap().value.unwrap(), 1);
+    assert_eq!(ctx.eval("y = x >= 6").unwrap().value.unwrap(), 0);
+    assert_eq!(ctx["y"], 0);
+}
@@ -0,0 +1,190 @@
+use jnk::context::MathContext;
+use jnk::value::Value;
+
+#[test]
+fn list_literals() {
+    let mut ctx = MathContext::new();
+
+    let result = ctx.eval("xs = [3, 1, 2]").unwrap();
+    assert_eq!(result.result().to_string(), "[3, 1, 2]");
+    assert!(matches!(ctx.var_get_value("xs"), Some(Value::List(x)) if x.len() == 3));
+    // a list result has no value, rather than a made up one
+    assert_eq!(result.value, None);
+    assert_eq!(result.list.as_ref().map(Vec::len), Some(3));
+    // list variables aren't numbers
+    assert!(ctx.var_get("xs").is_none());
+    assert_eq!(ctx.last(), None);
+    assert_eq!(ctx.last_value().to_string(), "[3, 1, 2]");
+    ctx.eval("n = 2 m").unwrap();
+    let vars = ctx.vars_quantity().collect::<Vec<_>>();
+    assert_eq!(vars.len(), 1);
+    assert_eq!(vars[0].1.to_string(), "2 m");
+
+    assert_eq!(ctx.eval("xs[0]").unwrap().value.unwrap(), 3);
+    assert_eq!(ctx.eval("xs[-1]").unwrap().value.unwrap(), 2);
+    assert_eq!(
+        ctx.eval("[[1, 2], [3, 4]][1][0]").unwrap().value.unwrap(),
+        3
+    );
+    assert_eq!(
+        ctx.eval("1..5").unwrap().result().to_string(),
+        "[1, 2, 3, 4]"
+    );
+    assert_eq!(ctx.eval("1..=3").unwrap().result().to_string(), "[1, 2, 3]");
+    assert_eq!(ctx.eval("3..1").unwrap().result().to_string(), "[]");
+    assert_eq!(ctx.eval("[]").unwrap().result().to_string(), "[]");
+
+    let err = ctx.eval("xs[3]").unwrap_err();
+    assert_eq!(err.name(), "IndexOutOfBounds");
+    assert_eq!(
+        err.to_string(),
+        "index '3' out of bounds for a list of length 3"
+    );
+    assert_eq!(
+        ctx.eval("xs[0 - 4]").unwrap_err().name(),
+        "IndexOutOfBounds"
+    );
+    assert_eq!(ctx.eval("5[0]").unwrap_err().name(), "ExpectedList");
+
+    ctx.set_max_iterations(10);
+    assert_eq!(ctx.eval("len(1..=10)").unwrap().value.unwrap(), 10);
+    let err = ctx.eval("0..2 ^ 40").unwrap_err();
+    assert_eq!(err.name(), "IterationLimit");
+    assert_eq!(err.to_string(), "exceeded the limit of 10 iterations");
+}
+
+#[test]
+fn broadcasting() {
+    let mut ctx = MathContext::new();
+    ctx.eval("xs = [1, 2, 3]").unwrap();
+
+    assert_eq!(
+        ctx.eval("xs * 2").unwrap().result().to_string(),
+        "[2, 4, 6]"
+    );
+    assert_eq!(
+        ctx.eval("10 - xs").unwrap().result().to_string(),
+        "[9, 8, 7]"
+    );
+    assert_eq!(
+        ctx.eval("2 ^ xs").unwrap().result().to_string(),
+        "[2, 4, 8]"
+    );
+    assert_eq!(
+        ctx.eval("-xs").unwrap().result().to_string(),
+        "[-1, -2, -3]"
+    );
+    assert_eq!(
+        ctx.eval("xs > 1").unwrap().result().to_string(),
+        "[0, 1, 1]"
+    );
+    assert_eq!(
+        ctx.eval("xs + [10, 20, 30]").unwrap().result().to_string(),
+        "[11, 22, 33]"
+    );
+    assert_eq!(
+        ctx.eval("xs * 1 km to m").unwrap().result().to_string(),
+        "[1000 m, 2000 m, 3000 m]"
+    );
+
+    let err = ctx.eval("xs + [1, 2]").unwrap_err();
+    assert_eq!(err.name(), "LengthMismatch");
+
+    // two lists are compared as a whole
+    assert_eq!(ctx.eval_disregard("xs == [1, 2, 3]").unwrap(), 1);
+    assert_eq!(ctx.eval_disregard("xs == [1, 2]").unwrap(), 0);
+    assert_eq!(ctx.eval_disregard("xs != [1, 2, 4]").unwrap(), 1);
+    assert_eq!(
+        ctx.eval_disregard("[1 km, [2]] == [1000 m, [2]]").unwrap(),
+        1
+    );
+    assert_eq!(
+        ctx.eval("xs == 2").unwrap().result().to_string(),
+        "[0, 1, 0]"
+    );
+    let err = ctx.eval("[1 m] == [1 s]").unwrap_err();
+    assert_eq!(err.name(), "DimensionMismatch");
+    assert_eq!(ctx.eval("xs / 0").unwrap_err().name(), "DivisionByZero");
+}
+
+#[test]
+fn list_functions() {
+    let mut ctx = MathContext::new();
+    ctx.eval("xs = [3, 1, 2]").unwrap();
+
+    assert_eq!(ctx.eval("len(xs)").unwrap().value.unwrap(), 3);
+    assert_eq!(ctx.eval("sum(1..=10)").unwrap().value.unwrap(), 55);
+    assert_eq!(ctx.eval("prod(xs)").unwrap().value.unwrap(), 6);
+    assert_eq!(ctx.eval("sum([])").unwrap().value.unwrap(), 0);
+    assert_eq!(ctx.eval("prod([])").unwrap().value.unwrap(), 1);
+    assert_eq!(
+        ctx.eval("sum([1 km, 250 m])").unwrap().result().to_string(),
+        "1250 m"
+    );
+    assert_eq!(ctx.eval("min(xs)").unwrap().value.unwrap(), 1);
+    assert_eq!(
+        ctx.eval("max(1 m, 3 ft)").unwrap().result().to_string(),
+        "1 m"
+    );
+    assert_eq!(ctx.eval("max(xs)").unwrap().value.unwrap(), 3);
+    assert_eq!(ctx.eval("max(4, 9, 2)").unwrap().value.unwrap(), 9);
+    assert_eq!(
+        ctx.eval("sort(xs)").unwrap().result().to_string(),
+        "[1, 2, 3]"
+    );
+    assert_eq!(
+        ctx.eval("sort([1 km, 20 m])").unwrap().result().to_string(),
+        "[20 m, 1 km]"
+    );
+    assert_eq!(
+        ctx.eval("sort([1 m, 1 yd, 2 ft])")
+            .unwrap()
+            .result()
+            .to_string(),
+        "[2 ft, 1 yd, 1 m]"
+    );
+    assert_eq!(
+        ctx.eval("reverse(xs)").unwrap().result().to_string(),
+        "[2, 1, 3]"
+    );
+
+    assert_eq!(
+        ctx.eval("map(x, xs, x ^ 2)").unwrap().result().to_string(),
+        "[9, 1, 4]"
+    );
+    assert_eq!(
+        ctx.eval("filter(x, 1..20, isprime(x))")
+            .unwrap()
+            .result()
+            .to_string(),
+        "[2, 3, 5, 7, 11, 13, 17, 19]"
+    );
+    // the bound variable doesn't leak into (or read from) the context
+    ctx.eval("x = 100").unwrap();
+    assert_eq!(
+        ctx.eval("map(x, xs, x + 1)").unwrap().result().to_string(),
+        "[4, 2, 3]"
+    );
+    assert_eq!(ctx["x"], 100);
+    assert_eq!(
+        ctx.eval("map(x, xs, map(x, 1..=x, x))")
+            .unwrap()
+            .result()
+            .to_string(),
+        "[[1, 2, 3], [1], [1, 2]]"
+    );
+
+    assert_eq!(ctx.eval("min([])").unwrap_err().name(), "EmptyList");
+    assert_eq!(ctx.eval("len(5)").unwrap_err().name(), "ExpectedList");
+    assert_eq!(
+        ctx.eval("map(1, xs, 2)").unwrap_err().name(),
+        "ExpectedVariable"
+    );
+    assert_eq!(
+        ctx.eval("sort([1 m, 2 s])").unwrap_err().name(),
+        "DimensionMismatch"
+    );
+
+    let analysis = MathContext::analyze("map(x, xs, x * y)").unwrap();
+    assert_eq!(analysis.reads, ["xs", "y"]);
+}
@@ -0,0 +1,97 @@
+use jnk::context::MathContext;
+use jnk::Integer;
+
+#[test]
+fn primes() {
+    let ctx = MathContext::new();
+
+    assert_eq!(ctx.eval_disregard("isprime(2 ^ 127 - 1)").unwrap(), 1);
+    assert_eq!(ctx.eval_disregard("isprime(2 ^ 128 + 1)").unwrap(), 0);
+    assert_eq!(ctx.eval_disregard("isprime(1)").unwrap(), 0);
+    assert_eq!(ctx.eval_disregard("isprime(97, 5)").unwrap(), 1);
+    assert_eq!(ctx.eval_disregard("nextprime(13)").unwrap(), 17);
+    assert_eq!(ctx.eval_disregard("prevprime(13)").unwrap(), 11);
+    assert_eq!(ctx.eval_disregard("prevprime(3)").unwrap(), 2);
+}
+
+#[test]
+fn factorization() {
+    let mut ctx = MathContext::new();
+
+    let result = ctx.eval("factor(360)").unwrap();
+    assert_eq!(result.result().to_string(), "[[2, 3], [3, 2], [5, 1]]");
+    let result = ctx.eval("factor(0 - 12)").unwrap();
+    assert_eq!(result.result().to_string(), "[[-1, 1], [2, 2], [3, 1]]");
+    // too large for trial division alone
+    let result = ctx.eval("factor(1000003 * 1000033)").unwrap();
+    assert_eq!(result.result().to_string(), "[[1000003, 1], [1000033, 1]]");
+
+    let result = ctx.eval("divisors(12)").unwrap();
+    assert_eq!(result.result().to_string(), "[1, 2, 3, 4, 6, 12]");
+    assert_eq!(ctx.eval_disregard("totient(36)").unwrap(), 12);
+    assert_eq!(ctx.eval_disregard("totient(1)").unwrap(), 1);
+}
+
+#[test]
+fn symbols() {
+    let mut ctx = MathContext::new();
+
+    assert_eq!(ctx.eval_disregard("jacobi(1001, 9907)").unwrap(), -1);
+    assert_eq!(ctx.eval_disregard("legendre(2, 7)").unwrap(), 1);
+    assert_eq!(ctx.eval_disregard("legendre(14, 7)").unwrap(), 0);
+    assert_eq!(ctx.eval_disregard("kronecker(3, 8)").unwrap(), -1);
+
+    let result = ctx.eval("egcd(240, 46)").unwrap();
+    assert_eq!(result.result().to_string(), "[2, -9, 47]");
+}
+
+#[test]
+fn invalid_arguments() {
+    let ctx = MathContext::new();
+
+    for expr in [
+        "factor(0)",
+        "totient(0)",
+        "divisors(0)",
+        "prevprime(2)",
+        "isprime(7, 0)",
+        "isprime(0 - 7)",
+        "jacobi(3, 8)",
+        "legendre(3, 9)",
+    ] {
+        let err = ctx.eval_disregard(expr).unwrap_err();
+        assert_eq!(err.name(), "InvalidArgument", "{expr}");
+    }
+
+    let err = ctx.eval_disregard("factor(12) + 1").unwrap_err();
+    assert_eq!(err.name(), "ExpectedNumber");
+}
+
+#[test]
+fn factorization_limit() {
+    let mut ctx = MathContext::new();
+    ctx.set_max_iterations(1000);
+
+    // 2 ^ 128 + 1 = 59649589127497217 * 5704689200685129054721
+    for expr in [
+        "factor(2 ^ 128 + 1)",
+        "totient(2 ^ 128 + 1)",
+        "divisors(2 ^ 128 + 1)",
+    ] {
+        let err = ctx.eval_disregard(expr).unwrap_err();
+        assert_eq!(err.name(), "IterationLimit", "{expr}");
+    }
+    // small factors don't need any iterations
+    assert_eq!(
+        ctx.eval_disregard("totient(2 ^ 64)").unwrap(),
+        Integer::from(1) << 63
+    );
+    ctx.set_max_iterations(1_000_000);
+    assert_eq!(
+        ctx.eval("factor(1000003 * 1000033)")
+            .unwrap()
+            .result()
+            .to_string(),
+        "[[1000003, 1], [1000033, 1]]"
+    );
+}
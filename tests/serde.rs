@@ -0,0 +1,92 @@
+#![cfg(feature = "serde")]
+
+use jnk::context::{Eval, MathContext};
+use jnk::Integer;
+
+#[test]
+fn context_round_trip() {
+    let mut ctx = MathContext::new();
+    ctx.eval("big = 2 ^ 200 + 1").unwrap();
+    ctx.eval("small = 0 - 7").unwrap();
+    ctx.eval("big * 2").unwrap();
+
+    let json = serde_json::to_string(&ctx).unwrap();
+    assert_eq!(
+        json,
+        format!(
+            r#"{{"vars":{{"big":"{0}","small":"-7"}},"last":"{1}"}}"#,
+            (Integer::from(1) << 200) + 1,
+            ((Integer::from(1) << 200) + 1) * 2,
+        )
+    );
+
+    let restored: MathContext = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.var_get("big"), ctx.var_get("big"));
+    assert_eq!(restored.var_get("small"), ctx.var_get("small"));
+    assert_eq!(restored.last(), ctx.last());
+}
+
+#[test]
+fn context_rejects_invalid() {
+    assert!(serde_json::from_str::<MathContext>(r#"{"vars":{"1x":"1"},"last":"0"}"#).is_err());
+    assert!(serde_json::from_str::<MathContext>(r#"{"vars":{"x":"1.5"},"last":"0"}"#).is_err());
+}
+
+#[test]
+fn eval_round_trip() {
+    let mut ctx = MathContext::new();
+    let result = ctx.eval("x = 10 ^ 30").unwrap();
+
+    let json = serde_json::to_string(&result).unwrap();
+    assert_eq!(
+        json,
+        r#"{"value":"1000000000000000000000000000000","var":"x"}"#
+    );
+
+    let restored: Eval = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.value, result.value);
+    assert_eq!(restored.var, result.var);
+}
+
+#[test]
+fn unit_round_trip() {
+    let mut ctx = MathContext::new();
+    let result = ctx.eval("speed = 90 km / 1 h").unwrap();
+
+    let json = serde_json::to_string(&result).unwrap();
+    assert_eq!(json, r#"{"value":"90","unit":"km/h","var":"speed"}"#);
+    let restored: Eval = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.unit, result.unit);
+
+    let json = serde_json::to_string(&ctx).unwrap();
+    assert_eq!(json, r#"{"vars":{"speed":"90 km/h"},"last":"90 km/h"}"#);
+    let restored: MathContext = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        restored.var_get_quantity("speed"),
+        ctx.var_get_quantity("speed")
+    );
+
+    assert!(
+        serde_json::from_str::<MathContext>(r#"{"vars":{"x":"1 furlong"},"last":"0"}"#).is_err()
+    );
+}
+
+#[test]
+fn list_round_trip() {
+    let mut ctx = MathContext::new();
+    let result = ctx.eval("xs = [1 km, [2]]").unwrap();
+
+    let json = serde_json::to_string(&result).unwrap();
+    assert_eq!(json, r#"{"list":["1 km",["2"]],"var":"xs"}"#);
+    let restored: Eval = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.value, None);
+    assert_eq!(restored.list, result.list);
+
+    let json = serde_json::to_string(&ctx).unwrap();
+    assert_eq!(
+        json,
+        r#"{"vars":{"xs":["1 km",["2"]]},"last":["1 km",["2"]]}"#
+    );
+    let restored: MathContext = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.var_get_value("xs"), ctx.var_get_value("xs"));
+}
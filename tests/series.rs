@@ -0,0 +1,95 @@
+use jnk::context::MathContext;
+
+#[test]
+fn sums_and_products() {
+    let mut ctx = MathContext::new();
+
+    assert_eq!(
+        ctx.eval("sum(k, 1, 100, k ^ 2)").unwrap().value.unwrap(),
+        338350
+    );
+    ctx.eval("n = 5").unwrap();
+    assert_eq!(
+        ctx.eval("prod(k, 1, n, 2 * k - 1)").unwrap().value.unwrap(),
+        945
+    );
+    assert_eq!(ctx.eval("sum(k, 5, 1, k)").unwrap().value.unwrap(), 0);
+    assert_eq!(ctx.eval("prod(k, 5, 1, k)").unwrap().value.unwrap(), 1);
+    assert_eq!(
+        ctx.eval("sum(k, 1, 3, k * 1 km)")
+            .unwrap()
+            .result()
+            .to_string(),
+        "6 km"
+    );
+
+    // the index is only bound inside the body
+    assert!(!ctx.var_contains("k"));
+    ctx.eval("k = 100").unwrap();
+    assert_eq!(ctx.eval("sum(k, 1, 3, k)").unwrap().value.unwrap(), 6);
+    assert_eq!(ctx["k"], 100);
+
+    let analysis = MathContext::analyze("sum(k, 1, n, k * x)").unwrap();
+    assert_eq!(analysis.reads, ["n", "x"]);
+}
+
+#[test]
+fn nested_sums() {
+    let ctx = MathContext::new();
+
+    assert_eq!(
+        ctx.eval_disregard("sum(i, 1, 3, sum(j, 1, i, i * j))")
+            .unwrap(),
+        25
+    );
+    // an inner index shadows an outer one of the same name, except in the
+    // inner bounds
+    assert_eq!(
+        ctx.eval_disregard("sum(k, 1, 3, sum(k, 1, k, k))").unwrap(),
+        10
+    );
+    assert_eq!(
+        ctx.eval_disregard("sum(k, 1, 3, let k = 2 in k)").unwrap(),
+        6
+    );
+}
+
+#[test]
+fn iteration_limit() {
+    let mut ctx = MathContext::new();
+    assert_eq!(ctx.max_iterations(), 1_000_000);
+
+    ctx.set_max_iterations(10);
+    assert_eq!(ctx.eval_disregard("sum(k, 1, 10, k)").unwrap(), 55);
+    let err = ctx.eval_disregard("sum(k, 1, 11, k)").unwrap_err();
+    assert_eq!(err.name(), "IterationLimit");
+    assert_eq!(err.to_string(), "exceeded the limit of 10 iterations");
+    assert_eq!(
+        ctx.eval_disregard("len(1..=11)").unwrap_err().name(),
+        "IterationLimit"
+    );
+
+    // iterations are shared by the whole expression, nested sums included
+    ctx.set_max_iterations(1000);
+    assert_eq!(ctx.eval_disregard("sum(k, 1, 1000, k)").unwrap(), 500500);
+    let err = ctx.eval_disregard("sum(i, 1, 100, sum(j, 1, 100, 1))");
+    assert_eq!(err.unwrap_err().name(), "IterationLimit");
+    assert_eq!(
+        ctx.eval_disregard("sum(i, 1, 10, sum(j, 1, 90, 1))")
+            .unwrap(),
+        900
+    );
+    let err = ctx.eval_disregard("sum(k, 1, 600, k) + len(1..=600)");
+    assert_eq!(err.unwrap_err().name(), "IterationLimit");
+    // but not between expressions
+    assert_eq!(ctx.eval_disregard("sum(k, 1, 600, k)").unwrap(), 180300);
+    assert_eq!(ctx.eval_disregard("len(1..=600)").unwrap(), 600);
+
+    // bodies are evaluated lazily, so nothing is evaluated past an error
+    let err = ctx.eval_disregard("sum(k, 0 - 1, 1, 1 / k)").unwrap_err();
+    assert_eq!(err.name(), "DivisionByZero");
+    assert_eq!(
+        ctx.eval_disregard("sum(1, 1, 2, 3)").unwrap_err().name(),
+        "ExpectedVariable"
+    );
+}
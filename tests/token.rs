@@ -0,0 +1,25 @@
+use jnk::token::{tokenize, TokenKind};
+
+#[test]
+fn tokenize_invalid() {
+    let expr = "let x = 1 in (x $ letter";
+    let tokens = tokenize(expr)
+        .into_iter()
+        .map(|x| (x.kind, &expr[x.span]))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        tokens,
+        [
+            (TokenKind::Keyword, "let"),
+            (TokenKind::Variable, "x"),
+            (TokenKind::Punctuation, "="),
+            (TokenKind::Literal, "1"),
+            (TokenKind::Keyword, "in"),
+            (TokenKind::Paren, "("),
+            (TokenKind::Variable, "x"),
+            (TokenKind::Unknown, "$"),
+            (TokenKind::Variable, "letter"),
+        ]
+    );
+}
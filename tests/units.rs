@@ -0,0 +1,113 @@
+use jnk::context::MathContext;
+use jnk::unit::Unit;
+
+#[test]
+fn unit_arithmetic() {
+    let mut ctx = MathContext::new();
+
+    let result = ctx.eval("3 km + 250 m").unwrap();
+    assert_eq!(result.value.unwrap(), 3250);
+    assert_eq!(result.unit.to_string(), "m");
+
+    let result = ctx.eval("rate = 10 MiB / 2 s").unwrap();
+    assert_eq!(result.value.unwrap(), 5);
+    assert_eq!(result.unit.to_string(), "MiB/s");
+    assert_eq!(ctx.var_get_quantity("rate").unwrap().unit, result.unit);
+
+    let result = ctx.eval("rate * 1 min").unwrap();
+    assert_eq!(result.value.unwrap(), 5);
+    assert_eq!(result.unit.to_string(), "MiB*min/s");
+
+    let result = ctx.eval("(2 m) ^ 2 / 4 m^2").unwrap();
+    assert_eq!(result.value.unwrap(), 1);
+    assert!(result.unit.is_empty());
+
+    assert_eq!(ctx.eval("1 km/m == 1000").unwrap().value.unwrap(), 1);
+    assert_eq!(ctx.eval("90 min > 1 h").unwrap().value.unwrap(), 1);
+}
+
+#[test]
+fn mixed_unit_comparisons() {
+    let ctx = MathContext::new();
+
+    // compared exactly, not after converting to the smaller unit
+    assert_eq!(ctx.eval_disregard("1 m == 3 ft").unwrap(), 0);
+    assert_eq!(ctx.eval_disregard("1 m != 3 ft").unwrap(), 1);
+    assert_eq!(ctx.eval_disregard("1 m > 3 ft").unwrap(), 1);
+    assert_eq!(ctx.eval_disregard("3 ft < 1 m").unwrap(), 1);
+    assert_eq!(ctx.eval_disregard("1 mi == 5280 ft").unwrap(), 1);
+    assert_eq!(ctx.eval_disregard("1 km >= 1000 m").unwrap(), 1);
+    assert_eq!(ctx.eval_disregard("1 bit < 1 B").unwrap(), 1);
+    assert_eq!(
+        ctx.eval_disregard("1 m < 1 s").unwrap_err().name(),
+        "DimensionMismatch"
+    );
+}
+
+#[test]
+fn mixed_unit_arithmetic() {
+    let mut ctx = MathContext::new();
+
+    // converted exactly to a unit both are whole multiples of
+    for (expr, expected) in [
+        ("1 km + 1 m", "1001 m"),
+        ("1 ft + 1 inch", "13 inch"),
+        ("1 KiB + 1 kB", "2024 B"),
+        ("1 ft + 1 m", "1304800 µm"),
+        ("1 m - 1 ft", "695200 µm"),
+        ("1 mi + 1 km", "2609344 mm"),
+        ("1 kB - 1 bit", "7999 bit"),
+        ("sum([1 KiB, 1 kB])", "2024 B"),
+        ("[1 ft, 1 yd] + 1 m", "[1304800 µm, 1914400 µm]"),
+    ] {
+        let result = ctx.eval(expr).unwrap().result();
+        assert_eq!(result.to_string(), expected, "{expr}");
+    }
+    assert_eq!(ctx.eval_disregard("1 ft + 1 m == 1304800 um").unwrap(), 1);
+    assert_eq!(ctx.eval_disregard("1 mi + 1 km to m").unwrap(), 2609);
+}
+
+#[test]
+fn unit_conversion() {
+    let ctx = MathContext::new();
+
+    assert_eq!(ctx.eval_disregard("2 km to ft").unwrap(), 6561);
+    assert_eq!(ctx.eval_disregard("1 kB to bit").unwrap(), 8000);
+    assert_eq!(ctx.eval_disregard("3 m + 2 m to cm").unwrap(), 500);
+    assert_eq!(ctx.eval_disregard("1 kg * 1 m / 1 s^2 to N").unwrap(), 1);
+
+    let unit: Unit = "kg*m/s^2".parse().unwrap();
+    assert_eq!(unit.to_string(), "kg*m/s^2");
+    assert_eq!("s^-1".parse::<Unit>().unwrap().to_string(), "s^-1");
+}
+
+#[test]
+fn unit_errors() {
+    let ctx = MathContext::new();
+
+    let err = ctx.eval_disregard("3 m + 2 s").unwrap_err();
+    assert_eq!(err.name(), "DimensionMismatch");
+    assert_eq!(err.to_string(), "incompatible units 'm' and 's'");
+    // compound units are only added if one is a multiple of the other
+    let err = ctx.eval_disregard("1 MiB/s + 1 kB/s").unwrap_err();
+    assert_eq!(err.name(), "NoCommonUnit");
+    assert_eq!(
+        err.to_string(),
+        "no unit both 'MiB/s' and 'kB/s' can be converted to exactly"
+    );
+    assert_eq!(ctx.eval_disregard("1 MiB/s + 1 KiB/s").unwrap(), 1025);
+
+    assert_eq!(
+        ctx.eval_disregard("2 h to m").unwrap_err().name(),
+        "DimensionMismatch"
+    );
+    assert_eq!(
+        ctx.eval_disregard("powmod(2 m, 3, 5)").unwrap_err().name(),
+        "DimensionMismatch"
+    );
+    assert_eq!(
+        ctx.eval_disregard("3 furlong").unwrap_err().name(),
+        "UnknownUnit"
+    );
+    assert!(ctx.eval_disregard("x = 1 to").is_err());
+}
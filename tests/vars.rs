@@ -0,0 +1,69 @@
+use jnk::context::MathContext;
+use jnk::Integer;
+
+#[test]
+fn list_vars() {
+    let mut ctx = MathContext::new();
+    ctx.eval("zeta = 3").unwrap();
+    ctx.eval("alpha = 1").unwrap();
+    ctx.eval("_ = 7").unwrap();
+
+    let vars = ctx
+        .vars()
+        .map(|(k, v)| (k.to_owned(), v.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vars,
+        [
+            ("alpha".to_owned(), Integer::from(1)),
+            ("zeta".to_owned(), Integer::from(3)),
+        ]
+    );
+}
+
+#[test]
+fn remove_and_clear_vars() {
+    let mut ctx = MathContext::new();
+    ctx.eval("x = 1").unwrap();
+    ctx.eval("y = 2").unwrap();
+
+    assert!(ctx.var_contains("x"));
+    assert_eq!(ctx.var_remove("x").unwrap(), Integer::from(1));
+    assert!(!ctx.var_contains("x"));
+    assert_eq!(ctx.var_remove("x"), None);
+    // lists are removed (and returned) too
+    ctx.eval("xs = [1, 2]").unwrap();
+    assert_eq!(ctx.var_remove("xs").unwrap().to_string(), "[1, 2]");
+    assert!(!ctx.var_contains("xs"));
+
+    ctx.with_scope(|scoped| {
+        scoped.eval("z = 3").unwrap();
+        assert_eq!(scoped.vars().count(), 2);
+        scoped.var_clear();
+        assert!(scoped.var_contains("y"));
+        assert!(!scoped.var_contains("z"));
+    });
+
+    ctx.var_clear();
+    assert_eq!(ctx.vars().count(), 0);
+}
+
+#[test]
+fn extend_and_index() {
+    let mut ctx = MathContext::new();
+    ctx.extend([("a".to_owned(), 1.into()), ("b".to_owned(), 2.into())])
+        .unwrap();
+    assert_eq!(ctx["a"], 1);
+    assert_eq!(ctx["b"], 2);
+
+    let err = ctx.extend([("c".to_owned(), 3.into()), ("4d".to_owned(), 4.into())]);
+    assert!(err.is_err());
+    assert!(!ctx.var_contains("c"));
+}
+
+#[test]
+#[should_panic]
+fn index_missing() {
+    let ctx = MathContext::new();
+    let _ = &ctx["missing"];
+}